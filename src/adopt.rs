@@ -0,0 +1,226 @@
+use crate::config::Config;
+use crate::index::Index;
+use crate::local::find_root;
+use crate::remote::{check_collision, parse_url, record_clone};
+use crate::utils::{copy_dir, is_empty_dir};
+use crate::vcs::{detect_vcs_from_path, VCSBackend, VCSOption};
+use anyhow::{Context, Result};
+use log::debug;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, PartialEq)]
+enum Adopted {
+    Moved,
+    AlreadyThere,
+}
+
+pub fn detect_backend(path: &Path) -> Option<VCSBackend> {
+    [".git", ".hg"]
+        .iter()
+        .filter(|name| path.join(name).exists())
+        .find_map(|name| detect_vcs_from_path(name))
+}
+
+/// Copies a clone, removing a partial copy on failure so that it is not taken for a clone.
+fn copy_clone(src: &Path, dest: &Path) -> Result<()> {
+    if let Err(e) = copy_dir(src, dest) {
+        if let Err(e) = fs::remove_dir_all(dest) {
+            debug!("{} path:{:?}", e, dest);
+        }
+        return Err(e.context(format!(
+            "failed copy {} to {}",
+            src.display(),
+            dest.display()
+        )));
+    }
+    Ok(())
+}
+
+/// Moves (or copies) the clone at `src` to `opt.path` and updates the index.
+/// `url` is the remote of the checkout.
+pub fn move_clone(
+    config: &Config<'_>,
+    index: &mut Index,
+    src: &Path,
+    opt: &VCSOption,
+    url: String,
+    vcs: &VCSBackend,
+    copy: bool,
+) -> Result<()> {
+    let dest = &opt.path;
+    let src_root = find_root(config, src);
+    let src_fresh = src_root.map(|root| index.is_fresh(root)).unwrap_or(false);
+    let dest_root = find_root(config, dest);
+    let dest_fresh = dest_root.map(|root| index.is_fresh(root)).unwrap_or(false);
+
+    if dest.exists() {
+        fs::remove_dir(dest)?;
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if copy {
+        copy_clone(src, dest)?;
+    } else if let Err(e) = fs::rename(src, dest) {
+        // e.g. another file system
+        debug!("rename failed {}, copy {:?}", e, src);
+        copy_clone(src, dest)?;
+        fs::remove_dir_all(src)?;
+    }
+
+    if let Some(root) = src_root.filter(|_| !copy) {
+        if src_fresh {
+            index.remove(root, src);
+        } else {
+            index.invalidate(root);
+        }
+    }
+    if let Some(root) = dest_root {
+        // index the remote of the checkout rather than a rewritten url
+        let opt = VCSOption {
+            url: Some(url),
+            ..opt.clone()
+        };
+        record_clone(index, root, dest_fresh, &opt, vcs)?;
+    }
+    Ok(())
+}
+
+fn adopt_dir(config: &Config<'_>, index: &mut Index, dir: &str, copy: bool) -> Result<Adopted> {
+    let src = fs::canonicalize(dir).with_context(|| format!("{} not found", dir))?;
+    let vcs =
+        detect_backend(&src).with_context(|| format!("{} is not a repository", src.display()))?;
+    let remote = vcs
+        .remote_url(&src)?
+        .with_context(|| format!("{} has no remote url", src.display()))?;
+    let url = config.original_url(&remote);
+    let opt = parse_url(config, config.profile, &url)?;
+    let dest = &opt.path;
+    if *dest == src {
+        println!("{} is already in place", src.display());
+        return Ok(Adopted::AlreadyThere);
+    }
+    if dest.starts_with(&src) {
+        return Err(anyhow::format_err!(
+            "{} can not be moved into itself {}",
+            src.display(),
+            dest.display()
+        ));
+    }
+    if dest.exists() && !is_empty_dir(dest) {
+        check_collision(config, &opt, &vcs)?;
+        println!("{} is already cloned at {}", url, dest.display());
+        return Ok(Adopted::AlreadyThere);
+    }
+
+    let action = if copy { "copy" } else { "move" };
+    if config.dry_run {
+        println!(
+            "{} : dry-run {} to {}",
+            src.display(),
+            action,
+            dest.display()
+        );
+        return Ok(Adopted::Moved);
+    }
+
+    move_clone(config, index, &src, &opt, remote, &vcs, copy)?;
+    println!("{} : {} to {}", src.display(), action, dest.display());
+    Ok(Adopted::Moved)
+}
+
+/// Moves (or copies) existing clones to where `rrc get` would place them.
+pub fn adopt(config: &Config<'_>, dirs: &[&str], copy: bool) -> Result<()> {
+    let mut index = Index::load();
+    let (mut adopted, mut skipped, mut failed) = (0, 0, 0);
+    for dir in dirs {
+        match adopt_dir(config, &mut index, dir, copy) {
+            Ok(Adopted::Moved) => adopted += 1,
+            Ok(Adopted::AlreadyThere) => skipped += 1,
+            Err(e) => {
+                eprintln!("failed {} : {}", dir, e);
+                failed += 1;
+            }
+        }
+    }
+    if !config.dry_run {
+        index.save()?;
+    }
+    println!(
+        "adopted {}, skipped {}, failed {}",
+        adopted, skipped, failed
+    );
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!(
+            "failed to adopt {} directories",
+            failed
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepositoryConfig;
+    use crate::utils::{git, init_repo};
+
+    fn clone_at(path: &Path, url: &str) -> String {
+        init_repo(path);
+        git(path, &["remote", "add", "origin", url]);
+        path.to_string_lossy().into_owned()
+    }
+
+    #[test]
+    fn adopt_clones() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        let mut config: Config = Default::default();
+        config.repos.insert(
+            "default".to_owned(),
+            RepositoryConfig {
+                root: root.to_string_lossy().into_owned(),
+                ..Default::default()
+            },
+        );
+        let mut index = Index::default();
+        let adopt = |config: &Config<'_>, index: &mut Index, dir: &str, copy: bool| {
+            adopt_dir(config, index, dir, copy).unwrap()
+        };
+
+        let moved = clone_at(&dir.path().join("work/a"), "https://github.com/o/a");
+        config.dry_run = true;
+        assert_eq!(adopt(&config, &mut index, &moved, false), Adopted::Moved);
+        assert!(Path::new(&moved).exists());
+        assert!(!root.exists());
+        config.dry_run = false;
+        assert_eq!(adopt(&config, &mut index, &moved, false), Adopted::Moved);
+        assert!(!Path::new(&moved).exists());
+        assert!(root.join("github.com/o/a/.git/HEAD").exists());
+
+        let copied = clone_at(&dir.path().join("work/b"), "https://github.com/o/b");
+        assert_eq!(adopt(&config, &mut index, &copied, true), Adopted::Moved);
+        assert!(Path::new(&copied).join(".git/HEAD").exists());
+        assert!(root.join("github.com/o/b/.git/HEAD").exists());
+        // the copy is already in place
+        assert_eq!(
+            adopt(&config, &mut index, &copied, false),
+            Adopted::AlreadyThere
+        );
+
+        let other = clone_at(&dir.path().join("work/c"), "https://github.com/o/b");
+        git(
+            Path::new(&other),
+            &["remote", "set-url", "origin", "https://github.com/o/a"],
+        );
+        // a clone of another repository at the destination
+        git(
+            &root.join("github.com/o/a"),
+            &["remote", "set-url", "origin", "https://github.com/x/y"],
+        );
+        assert!(adopt_dir(&config, &mut index, &other, false).is_err());
+        assert!(Path::new(&other).exists());
+    }
+}
@@ -1,18 +1,212 @@
-use anyhow::{Context, Result};
+use crate::filter::Filter;
+use crate::ghq;
+use crate::layout::{self, DEFAULT_LAYOUT};
+use crate::vcs::VCSBackend;
+use crate::walk::{WalkOptions, DEFAULT_MAX_DEPTH};
+use anyhow::{Context, Error, Result};
 use dirs::home_dir;
+use globset::{GlobBuilder, GlobMatcher};
 use lazy_static::lazy_static;
+use log::debug;
+use regex::Regex;
 use serde_derive::Deserialize;
-use std::collections::{BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::default::Default;
 use std::fs::File;
 use std::io::Read;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::{env, path};
 use toml::from_str;
+use toml::value::{Table, Value};
 
 lazy_static! {
     pub static ref DEFAULT_REPO_ROOT: String = default_root();
 }
 
+/// Top-level section of static repository groups. It is not a profile.
+const GROUPS_SECTION: &str = "groups";
+/// Top-level array of routing rules. It is not a profile.
+const ROUTE_SECTION: &str = "route";
+/// Top-level section of per-host settings. It is not a profile.
+const HOST_SECTION: &str = "host";
+/// Top-level array of url rewrite rules. It is not a profile.
+const REWRITE_SECTION: &str = "rewrite";
+const META_FILE: &str = "rrc-meta.toml";
+
+const PROFILE_KEYS: &[&str] = &[
+    "root",
+    "hosts",
+    "layout",
+    "ignore",
+    "max_depth",
+    "default_host",
+    "default_scheme",
+    "default_owner",
+];
+const HOST_KEYS: &[&str] = &["protocol", "vcs", "kind", "ignore_case", "api_url", "token"];
+const ROUTE_KEYS: &[&str] = &["pattern", "regex", "profile"];
+const REWRITE_KEYS: &[&str] = &["from", "to"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ListFormat {
+    Path,
+    Relpath,
+    Url,
+    Json,
+    Ndjson,
+    Tags,
+}
+
+impl FromStr for ListFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<ListFormat> {
+        match s {
+            "path" => Ok(ListFormat::Path),
+            "relpath" => Ok(ListFormat::Relpath),
+            "url" => Ok(ListFormat::Url),
+            "json" => Ok(ListFormat::Json),
+            "ndjson" => Ok(ListFormat::Ndjson),
+            "tags" => Ok(ListFormat::Tags),
+            _ => Err(anyhow::format_err!("unknown format {}", s)),
+        }
+    }
+}
+
+/// Protocol of clone urls.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    Ssh,
+    Https,
+}
+
+/// Hosting service of a host, it decides how url paths map to repositories.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HostKind {
+    /// `owner/repo`
+    Github,
+    /// `group/subgroup/.../project`
+    Gitlab,
+    /// `org/project/_git/repo`
+    Azure,
+    /// `owner/repo`, also Forgejo
+    #[serde(alias = "forgejo")]
+    Gitea,
+    /// `workspace/repo`
+    Bitbucket,
+    /// the whole url path
+    Generic,
+}
+
+impl HostKind {
+    /// Kind of well-known hosts.
+    pub fn detect(host: &str) -> HostKind {
+        let host = host.to_ascii_lowercase();
+        match host.as_str() {
+            "github.com" => HostKind::Github,
+            "gitlab.com" => HostKind::Gitlab,
+            "dev.azure.com" | "ssh.dev.azure.com" => HostKind::Azure,
+            "gitea.com" | "codeberg.org" => HostKind::Gitea,
+            "bitbucket.org" => HostKind::Bitbucket,
+            _ if host.ends_with(".visualstudio.com") => HostKind::Azure,
+            _ => HostKind::Generic,
+        }
+    }
+}
+
+/// Settings of a host, `[host."github.com"]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HostConfig {
+    pub protocol: Option<Protocol>,
+    /// vcs of the repositories, detected from the url if not set
+    pub vcs: Option<VCSBackend>,
+    pub kind: Option<HostKind>,
+    /// the host treats owner and repository names case-insensitively
+    pub ignore_case: Option<bool>,
+    /// base url of the REST API
+    pub api_url: Option<String>,
+    /// token of the REST API
+    pub token: Option<String>,
+}
+
+///
+/// Replaces the `from` prefix of clone urls with `to`, like git's `insteadOf`.
+///
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rewrite {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct RouteConfig {
+    pattern: Option<String>,
+    regex: Option<String>,
+    profile: String,
+}
+
+#[derive(Debug, Clone)]
+pub enum RouteMatcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+    Host(String),
+}
+
+///
+/// Sends repositories matching a pattern to a profile.
+/// Patterns are matched against `host/owner/repo`.
+///
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub matcher: RouteMatcher,
+    pub profile: String,
+}
+
+impl Route {
+    pub fn is_match(&self, key: &str) -> bool {
+        match &self.matcher {
+            RouteMatcher::Glob(glob) => glob.is_match(key),
+            RouteMatcher::Regex(re) => re.is_match(key),
+            RouteMatcher::Host(host) => key
+                .split('/')
+                .next()
+                .map(|h| h.eq_ignore_ascii_case(host))
+                .unwrap_or(false),
+        }
+    }
+}
+
+impl RouteConfig {
+    fn build(self) -> Result<Route> {
+        let matcher = match (self.pattern, self.regex) {
+            (Some(pattern), None) => {
+                let glob = GlobBuilder::new(&pattern)
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("invalid route pattern {}", pattern))?;
+                RouteMatcher::Glob(glob.compile_matcher())
+            }
+            (None, Some(regex)) => RouteMatcher::Regex(
+                Regex::new(&regex)
+                    .map_err(|e| anyhow::format_err!("invalid route regex {}: {}", regex, e))?,
+            ),
+            _ => {
+                return Err(anyhow::format_err!(
+                    "route to '{}' requires either pattern or regex",
+                    self.profile
+                ))
+            }
+        };
+        Ok(Route {
+            matcher,
+            profile: self.profile,
+        })
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Config<'a> {
     pub repos: HashMap<String, RepositoryConfig>,
@@ -21,7 +215,19 @@ pub struct Config<'a> {
     pub profile: Option<&'a str>,
     pub each_cmd: Option<&'a Vec<&'a str>>,
     pub dry_run: bool,
-    pub hosts: HashMap<String, String>,
+    pub nested: bool,
+    pub format: ListFormat,
+    pub filter: Filter<'a>,
+    /// ordered routing rules, `[[route]]` first then the `hosts` of profiles
+    pub routes: Vec<Route>,
+    /// group name to repository patterns
+    pub groups: BTreeMap<String, Vec<String>>,
+    pub meta_path: PathBuf,
+    /// protocol given on the command line
+    pub protocol: Option<Protocol>,
+    pub host_configs: BTreeMap<String, HostConfig>,
+    /// url rewrite rules, the longest match wins
+    pub rewrites: Vec<Rewrite>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -30,6 +236,26 @@ pub struct RepositoryConfig {
     pub root: String,
     #[serde(default = "default_vec_str")]
     pub hosts: Vec<String>,
+    #[serde(default = "default_layout")]
+    pub layout: String,
+    #[serde(default = "default_vec_str")]
+    pub ignore: Vec<String>,
+    #[serde(default = "default_max_depth")]
+    pub max_depth: usize,
+    /// host of `owner/repo` shorthand
+    pub default_host: Option<String>,
+    /// scheme of `owner/repo` shorthand
+    pub default_scheme: Option<String>,
+    /// owner of `repo` shorthand
+    pub default_owner: Option<String>,
+}
+
+fn default_max_depth() -> usize {
+    DEFAULT_MAX_DEPTH
+}
+
+fn default_layout() -> String {
+    DEFAULT_LAYOUT.to_owned()
 }
 
 fn default_vec_str() -> Vec<String> {
@@ -56,7 +282,15 @@ impl Default for Config<'_> {
         let look = false;
         let each_cmd = None;
         let dry_run = false;
-        let hosts = HashMap::new();
+        let nested = false;
+        let format = ListFormat::Path;
+        let filter = Default::default();
+        let routes = vec![];
+        let groups = BTreeMap::new();
+        let meta_path = get_meta_path(&get_config_path());
+        let protocol = None;
+        let host_configs = BTreeMap::new();
+        let rewrites = vec![];
         Self {
             repos,
             query,
@@ -64,7 +298,15 @@ impl Default for Config<'_> {
             profile,
             each_cmd,
             dry_run,
-            hosts,
+            nested,
+            format,
+            filter,
+            routes,
+            groups,
+            meta_path,
+            protocol,
+            host_configs,
+            rewrites,
         }
     }
 }
@@ -73,11 +315,44 @@ impl Default for RepositoryConfig {
     fn default() -> Self {
         let root = default_root();
         let hosts = vec![];
-        Self { root, hosts }
+        let layout = default_layout();
+        let ignore = vec![];
+        let max_depth = default_max_depth();
+        Self {
+            root,
+            hosts,
+            layout,
+            ignore,
+            max_depth,
+            default_host: None,
+            default_scheme: None,
+            default_owner: None,
+        }
     }
 }
 
 impl Config<'_> {
+    /// A copy for tasks, without the profile, command and filter borrowed from the command line.
+    pub fn detach(&self) -> Config<'static> {
+        Config {
+            repos: self.repos.clone(),
+            query: self.query.clone(),
+            look: self.look,
+            profile: None,
+            each_cmd: None,
+            dry_run: self.dry_run,
+            nested: self.nested,
+            format: self.format,
+            filter: Default::default(),
+            routes: self.routes.clone(),
+            groups: self.groups.clone(),
+            meta_path: self.meta_path.clone(),
+            protocol: self.protocol,
+            host_configs: self.host_configs.clone(),
+            rewrites: self.rewrites.clone(),
+        }
+    }
+
     pub fn roots(&self) -> BTreeSet<&String> {
         let mut set = BTreeSet::new();
         for repo in self.repos.values() {
@@ -86,6 +361,98 @@ impl Config<'_> {
         set
     }
 
+    /// Walk options of a root, merged from every profile sharing it.
+    pub fn walk_options(&self, root: &str) -> WalkOptions {
+        let mut opts = WalkOptions {
+            max_depth: 0,
+            nested: self.nested,
+            ignore: vec![],
+        };
+        for repo in self.repos.values().filter(|r| r.root == root) {
+            opts.max_depth = opts.max_depth.max(repo.max_depth);
+            for pattern in &repo.ignore {
+                if !opts.ignore.contains(pattern) {
+                    opts.ignore.push(pattern.to_owned());
+                }
+            }
+        }
+        opts.ignore.sort();
+        opts
+    }
+
+    /// Returns the profile of the first route matching `key` (host/owner/repo).
+    pub fn route(&self, key: &str) -> Option<&str> {
+        self.routes
+            .iter()
+            .find(|route| route.is_match(key))
+            .map(|route| route.profile.as_str())
+    }
+
+    /// Returns the profile a repository under `root` belongs to.
+    /// A routed profile wins when several profiles share the root.
+    pub fn profile_of(&self, root: &str, key: &str) -> Option<&str> {
+        if let Some(name) = self.route(key) {
+            if self
+                .repos
+                .get(name)
+                .map(|r| r.root == root)
+                .unwrap_or(false)
+            {
+                return Some(name);
+            }
+        }
+        let mut names: Vec<&String> = self
+            .repos
+            .iter()
+            .filter(|(_, r)| r.root == root)
+            .map(|(name, _)| name)
+            .collect();
+        names.sort();
+        names.first().map(|name| name.as_str())
+    }
+
+    pub fn host_config(&self, host: &str) -> Option<&HostConfig> {
+        self.host_configs
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(host))
+            .map(|(_, conf)| conf)
+    }
+
+    pub fn host_vcs(&self, host: &str) -> Option<VCSBackend> {
+        self.host_config(host).and_then(|conf| conf.vcs.clone())
+    }
+
+    /// Kind of `host`, the configured one or the well-known one.
+    pub fn host_kind(&self, host: &str) -> HostKind {
+        self.host_config(host)
+            .and_then(|conf| conf.kind)
+            .unwrap_or_else(|| HostKind::detect(host))
+    }
+
+    /// Returns true if owner and repository names of `host` are lowercased, off unless set.
+    pub fn ignore_case(&self, host: &str) -> bool {
+        self.host_config(host)
+            .and_then(|conf| conf.ignore_case)
+            .unwrap_or(false)
+    }
+
+    /// Protocol of clone urls for `host`, the command line wins.
+    pub fn protocol(&self, host: &str) -> Option<Protocol> {
+        self.protocol
+            .or_else(|| self.host_config(host).and_then(|conf| conf.protocol))
+    }
+
+    /// Applies the rewrite rule with the longest matching `from`, like git's `insteadOf`.
+    pub fn rewrite(&self, url: &str) -> String {
+        replace_prefix(url, self.rewrites.iter().map(|r| (&r.from, &r.to)))
+    }
+
+    /// Reverts `rewrite`, so that a clone of a mirror is identified by the url
+    /// it was requested with.
+    pub fn original_url(&self, url: &str) -> String {
+        replace_prefix(url, self.rewrites.iter().map(|r| (&r.to, &r.from)))
+    }
+
     pub fn profile(&self, name: &str) -> Result<&RepositoryConfig> {
         if let Some(config) = self.repos.get(name) {
             Ok(config)
@@ -95,31 +462,179 @@ impl Config<'_> {
     }
 }
 
-pub fn parse_config(path: &str) -> Result<Config> {
-    let mut config: Config = Default::default();
+/// Replaces the longest matching prefix, the first rule on a tie.
+fn replace_prefix<'a>(url: &str, rules: impl Iterator<Item = (&'a String, &'a String)>) -> String {
+    let mut best: Option<(&String, &String)> = None;
+    for (from, to) in rules {
+        if url.starts_with(from.as_str()) && best.is_none_or(|(b, _)| from.len() > b.len()) {
+            best = Some((from, to));
+        }
+    }
+    match best {
+        Some((from, to)) => format!("{}{}", to, &url[from.len()..]),
+        None => url.to_owned(),
+    }
+}
+
+pub fn parse_config(path: &str) -> Result<Config<'_>> {
+    let config = Config {
+        meta_path: get_meta_path(path),
+        ..Default::default()
+    };
     if !path::Path::new(path).exists() {
+        // fall back to the settings of ghq on request, which runs git
+        if env::var("RRC_GHQ").map(|v| v == "1").unwrap_or(false) {
+            if let Some(table) = ghq::load()? {
+                debug!("config from {} {:?}", ghq::SOURCE, table);
+                return load_table(config, table, ghq::SOURCE);
+            }
+        }
+        debug!("no config {}, use the defaults", path);
         return Ok(config);
     }
+    debug!("config from {}", path);
     let mut config_toml = String::new();
     let mut file = File::open(path)?;
     file.read_to_string(&mut config_toml)?;
 
-    let repos: HashMap<String, RepositoryConfig> =
+    let table: Table =
         from_str(&config_toml).with_context(|| format!("failed parse toml. path: {}", path))?;
+    load_table(config, table, path)
+}
 
-    for (_, repo_conf) in repos.iter() {
-        let root = &repo_conf.root;
-        for host in &repo_conf.hosts {
-            if !config.hosts.contains_key(host) {
-                config.hosts.insert(host.to_string(), root.to_owned());
-            }
+/// Rejects profiles named like a reserved section, which would be read as that section.
+/// A section is taken for a profile if its values do not have the shape of the section.
+fn check_reserved(table: &Table, path: &str) -> Result<()> {
+    for name in [GROUPS_SECTION, HOST_SECTION, ROUTE_SECTION, REWRITE_SECTION] {
+        let is_profile = match table.get(name) {
+            // groups are arrays of patterns, hosts are tables
+            Some(Value::Table(section)) if name == GROUPS_SECTION => section
+                .values()
+                .any(|value| !matches!(value, Value::Array(_))),
+            Some(Value::Table(section)) if name == HOST_SECTION => section
+                .values()
+                .any(|value| !matches!(value, Value::Table(_))),
+            // routes and rewrites are arrays of tables
+            Some(Value::Table(_)) => true,
+            _ => false,
+        };
+        if is_profile {
+            return Err(anyhow::format_err!(
+                "[{}] is a reserved section and can not be a profile. path: {}",
+                name,
+                path
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Reads the reserved sections and the profiles of a config table.
+pub fn load_table<'a>(mut config: Config<'a>, mut table: Table, path: &str) -> Result<Config<'a>> {
+    check_reserved(&table, path)?;
+    if let Some(groups) = table.remove(GROUPS_SECTION) {
+        config.groups = groups
+            .try_into()
+            .with_context(|| format!("failed parse [{}]. path: {}", GROUPS_SECTION, path))?;
+    }
+    if let Some(hosts) = table.remove(HOST_SECTION) {
+        config.host_configs = hosts
+            .try_into()
+            .with_context(|| format!("failed parse [{}]. path: {}", HOST_SECTION, path))?;
+    }
+    if let Some(rewrites) = table.remove(REWRITE_SECTION) {
+        config.rewrites = rewrites
+            .try_into()
+            .with_context(|| format!("failed parse [[{}]]. path: {}", REWRITE_SECTION, path))?;
+    }
+    let routes: Vec<RouteConfig> = match table.remove(ROUTE_SECTION) {
+        Some(routes) => routes
+            .try_into()
+            .with_context(|| format!("failed parse [[{}]]. path: {}", ROUTE_SECTION, path))?,
+        None => vec![],
+    };
+    let repos: HashMap<String, RepositoryConfig> = Value::Table(table)
+        .try_into()
+        .with_context(|| format!("failed parse toml. path: {}", path))?;
+
+    let mut names: Vec<&String> = repos.keys().collect();
+    names.sort();
+    for name in &names {
+        let repo_conf = &repos[*name];
+        layout::validate(&repo_conf.layout)
+            .with_context(|| format!("invalid layout in profile '{}'", name))?;
+    }
+    for route in routes {
+        if !repos.contains_key(&route.profile) {
+            return Err(anyhow::format_err!(
+                "route to unknown profile '{}'",
+                route.profile
+            ));
+        }
+        config.routes.push(route.build()?);
+    }
+    // host filters in profile name order, the first profile listing a host wins
+    for name in names {
+        for host in &repos[name].hosts {
+            config.routes.push(Route {
+                matcher: RouteMatcher::Host(host.to_owned()),
+                profile: name.to_owned(),
+            });
         }
     }
+
     config.repos = repos;
 
     Ok(config)
 }
 
+fn unknown_in(value: &Value, known: &[&str], prefix: &str, keys: &mut Vec<String>) {
+    match value {
+        Value::Table(table) => {
+            for key in table.keys().filter(|key| !known.contains(&key.as_str())) {
+                keys.push(format!("{}.{}", prefix, key));
+            }
+        }
+        _ => keys.push(prefix.to_owned()),
+    }
+}
+
+/// Keys of a config file that are not recognised, as dotted paths.
+pub fn unknown_keys(table: &Table) -> Vec<String> {
+    let mut keys = vec![];
+    for (name, value) in table {
+        match (name.as_str(), value) {
+            (GROUPS_SECTION, _) => {}
+            (HOST_SECTION, Value::Table(hosts)) => {
+                for (host, conf) in hosts {
+                    let prefix = format!("{}.\"{}\"", HOST_SECTION, host);
+                    unknown_in(conf, HOST_KEYS, &prefix, &mut keys);
+                }
+            }
+            (ROUTE_SECTION, Value::Array(rules)) | (REWRITE_SECTION, Value::Array(rules)) => {
+                let known = if name == ROUTE_SECTION {
+                    ROUTE_KEYS
+                } else {
+                    REWRITE_KEYS
+                };
+                for (i, rule) in rules.iter().enumerate() {
+                    unknown_in(rule, known, &format!("{}[{}]", name, i), &mut keys);
+                }
+            }
+            _ => unknown_in(value, PROFILE_KEYS, name, &mut keys),
+        }
+    }
+    keys
+}
+
+/// The metadata file (tags) lives next to the config file.
+pub fn get_meta_path(config_path: &str) -> PathBuf {
+    match env::var("RRC_META") {
+        Ok(val) => PathBuf::from(val),
+        Err(_) => path::Path::new(config_path).with_file_name(META_FILE),
+    }
+}
+
 pub fn get_config_path() -> String {
     match env::var("RRC_CONFIG") {
         Ok(val) => val,
@@ -129,3 +644,120 @@ pub fn get_config_path() -> String {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::write;
+
+    #[test]
+    fn parse_routes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rrc.toml");
+        write(
+            &path,
+            r#"
+[default]
+root = "/repos"
+
+[work]
+root = "/work"
+
+[oss]
+root = "/oss"
+hosts = ["github.com"]
+
+[personal]
+root = "/personal"
+hosts = ["github.com", "gitlab.com"]
+
+[[route]]
+pattern = "github.com/mycompany/*"
+profile = "work"
+
+[[route]]
+regex = "^gitlab\\.com/mycompany/"
+profile = "work"
+"#,
+        )
+        .unwrap();
+        let path = path.to_str().unwrap();
+        let config = parse_config(path).unwrap();
+        assert_eq!(config.repos.len(), 4);
+        assert_eq!(config.route("github.com/mycompany/api"), Some("work"));
+        assert_eq!(config.route("gitlab.com/mycompany/group/api"), Some("work"));
+        // "oss" sorts before "personal"
+        assert_eq!(config.route("github.com/rust-lang/rust"), Some("oss"));
+        assert_eq!(config.route("gitlab.com/foo/bar"), Some("personal"));
+        assert_eq!(config.route("example.com/foo/bar"), None);
+    }
+
+    #[test]
+    fn parse_invalid_route() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("rrc.toml");
+        write(
+            &path,
+            r#"
+[[route]]
+pattern = "github.com/*"
+profile = "missing"
+"#,
+        )
+        .unwrap();
+        assert!(parse_config(path.to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn find_unknown_keys() {
+        let table: Table = from_str(
+            r#"
+typo = 1
+
+[default]
+root = "/repos"
+rot = "/repos"
+
+[groups]
+work = ["github.com/mycompany/*"]
+
+[host."github.com"]
+protocol = "ssh"
+protocl = "ssh"
+
+[[route]]
+pattern = "github.com/*"
+profile = "default"
+profle = "default"
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            unknown_keys(&table),
+            vec![
+                "default.rot",
+                "host.\"github.com\".protocl",
+                "route[0].profle",
+                "typo"
+            ]
+        );
+    }
+
+    #[test]
+    fn reserved_sections() {
+        let parse = |s: &str| load_table(Default::default(), from_str(s).unwrap(), "rrc.toml");
+        assert!(parse("[groups]\nwork = [\"github.com/mycompany/*\"]").is_ok());
+        assert!(parse("[host.\"github.com\"]\nprotocol = \"ssh\"").is_ok());
+        // group names may be profile keys
+        assert!(parse("[groups]\nroot = [\"github.com/mycompany/*\"]").is_ok());
+        for s in [
+            "[groups]\nroot = \"/repos\"\nhosts = [\"github.com\"]",
+            "[host]\nroot = \"/repos\"",
+            "[route]\nroot = \"/repos\"",
+            "[rewrite]\nroot = \"/repos\"",
+        ] {
+            let err = parse(s).unwrap_err();
+            assert!(err.to_string().contains("reserved section"), "{}", err);
+        }
+    }
+}
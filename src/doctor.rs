@@ -0,0 +1,312 @@
+use crate::config::{unknown_keys, Config};
+use crate::layout;
+use crate::local::LocalRepository;
+use crate::remote::{parse_url, remote_key};
+use crate::utils::{is_empty_dir, real_path, remove_empty_parents};
+use crate::vcs::VCSBackend;
+use crate::walk::walk;
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::value::Table;
+
+#[derive(Default)]
+struct Report {
+    problems: usize,
+    fixed: usize,
+}
+
+impl Report {
+    fn problem(&mut self, kind: &str, message: String) {
+        println!("[{}] {}", kind, message);
+        self.problems += 1;
+    }
+
+    fn fixed(&mut self, message: String) {
+        println!("  fixed: {}", message);
+        self.fixed += 1;
+    }
+}
+
+fn check_config_keys(path: &str, report: &mut Report) -> Result<()> {
+    if !Path::new(path).exists() {
+        return Ok(());
+    }
+    let s = fs::read_to_string(path).with_context(|| format!("failed read {}", path))?;
+    let table: Table =
+        toml::from_str(&s).with_context(|| format!("failed parse toml. path: {}", path))?;
+    for key in unknown_keys(&table) {
+        report.problem("unknown key", format!("{} in {}", key, path));
+    }
+    Ok(())
+}
+
+/// Hosts listed in several profiles go to the first profile by name.
+fn check_hosts(config: &Config<'_>, report: &mut Report) {
+    let mut hosts: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    let mut names: Vec<&String> = config.repos.keys().collect();
+    names.sort();
+    for name in names {
+        for host in &config.repos[name].hosts {
+            hosts
+                .entry(host.to_ascii_lowercase())
+                .or_default()
+                .push(name);
+        }
+    }
+    for (host, profiles) in hosts.iter().filter(|(_, p)| p.len() > 1) {
+        report.problem(
+            "ambiguous host",
+            format!(
+                "{} is listed in profiles {}, {} is used",
+                host,
+                profiles.join(", "),
+                profiles[0]
+            ),
+        );
+    }
+}
+
+/// A clone interrupted before git wrote its HEAD.
+fn is_broken(repo: &LocalRepository) -> bool {
+    let git_dir = repo.path.join(".git");
+    repo.backend == VCSBackend::GitBackend && git_dir.is_dir() && !git_dir.join("HEAD").exists()
+}
+
+/// Directories shallower than the clones of every profile of `root` are not clone targets.
+fn clone_depth(config: &Config<'_>, root: &str) -> usize {
+    config
+        .repos
+        .values()
+        .filter(|profile| profile.root == root)
+        .filter_map(|profile| layout::depth(&profile.layout).ok())
+        .min()
+        .unwrap_or(1)
+}
+
+/// Walks a root and reports failed clones. Returns the repositories found.
+fn check_root(
+    config: &Config<'_>,
+    root: &str,
+    fix: bool,
+    report: &mut Report,
+) -> Result<Vec<LocalRepository>> {
+    let root_path = match real_path(root) {
+        Some(path) if path.is_dir() => path,
+        _ => {
+            report.problem("unreachable root", format!("{} : not found", root));
+            return Ok(vec![]);
+        }
+    };
+    if let Err(e) = fs::read_dir(&root_path) {
+        report.problem("unreachable root", format!("{} : {}", root, e));
+        return Ok(vec![]);
+    }
+    let walked = walk(root, &config.walk_options(root))?;
+    let depth = clone_depth(config, root);
+    for dir in walked.dirs.keys() {
+        let at_clone_depth = dir
+            .strip_prefix(&root_path)
+            .map(|rel| rel.components().count() >= depth)
+            .unwrap_or(false);
+        if !at_clone_depth || !is_empty_dir(dir) {
+            continue;
+        }
+        report.problem(
+            "failed clone",
+            format!("{} : empty directory", dir.display()),
+        );
+        if fix {
+            fs::remove_dir(dir)?;
+            remove_empty_parents(&root_path, dir);
+            report.fixed(format!("removed {}", dir.display()));
+        }
+    }
+    for repo in walked.repos.iter().filter(|repo| is_broken(repo)) {
+        report.problem(
+            "failed clone",
+            format!("{} : missing HEAD", repo.path.display()),
+        );
+    }
+    Ok(walked.repos)
+}
+
+/// Reports repositories away from where `rrc get` would place them and
+/// repositories cloned more than once.
+fn check_remotes(config: &Config<'_>, repos: &[LocalRepository], report: &mut Report) {
+    let mut clones: BTreeMap<String, Vec<&PathBuf>> = BTreeMap::new();
+    for repo in repos.iter().filter(|repo| !is_broken(repo)) {
+        let url = match repo.backend.remote_url(&repo.path) {
+            Ok(Some(remote)) => config.original_url(&remote),
+            Ok(None) => continue,
+            Err(e) => {
+                debug!("{} path:{:?}", e, repo.path);
+                continue;
+            }
+        };
+        clones
+            .entry(remote_key(config, &url))
+            .or_default()
+            .push(&repo.path);
+        let placed = LocalRepository {
+            url: Some(url.clone()),
+            ..repo.clone()
+        };
+        match parse_url(config, placed.profile(config), &url) {
+            Ok(opt) if opt.path == repo.path => {}
+            Ok(opt) => report.problem(
+                "remote mismatch",
+                format!(
+                    "{} : {} belongs at {} (see rrc relocate)",
+                    repo.path.display(),
+                    url,
+                    opt.path.display()
+                ),
+            ),
+            Err(e) => report.problem(
+                "remote mismatch",
+                format!("{} : {}", repo.path.display(), e),
+            ),
+        }
+    }
+    for (key, paths) in clones.iter().filter(|(_, paths)| paths.len() > 1) {
+        let paths: Vec<String> = paths.iter().map(|p| p.display().to_string()).collect();
+        report.problem("duplicate clone", format!("{} : {}", key, paths.join(", ")));
+    }
+}
+
+///
+/// Checks the config and every root. `fix` removes the empty directories
+/// left by failed clones at the depth of a clone; the other problems are only reported.
+///
+pub fn doctor(config: &Config<'_>, config_path: &str, fix: bool) -> Result<()> {
+    let mut report: Report = Default::default();
+    check_config_keys(config_path, &mut report)?;
+    check_hosts(config, &mut report);
+    let mut repos = vec![];
+    for root in config.roots() {
+        repos.extend(check_root(config, root, fix, &mut report)?);
+    }
+    // nested roots walk the same repositories
+    repos.sort_by(|a, b| a.path.cmp(&b.path));
+    repos.dedup_by(|a, b| a.path == b.path);
+    check_remotes(config, &repos, &mut report);
+
+    if report.problems == 0 {
+        println!("no problems found");
+        return Ok(());
+    }
+    println!("found {}, fixed {}", report.problems, report.fixed);
+    if report.problems == report.fixed {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!(
+            "{} problems remain",
+            report.problems - report.fixed
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepositoryConfig;
+    use crate::utils::{git, init_repo};
+
+    fn test_config(dir: &Path) -> Config<'static> {
+        let mut config: Config = Default::default();
+        for (name, root, hosts) in [("default", "a", vec![]), ("work", "b", vec!["github.com"])] {
+            config.repos.insert(
+                name.to_owned(),
+                RepositoryConfig {
+                    root: dir.join(root).to_string_lossy().into_owned(),
+                    hosts: hosts.into_iter().map(str::to_owned).collect(),
+                    ..Default::default()
+                },
+            );
+        }
+        config
+    }
+
+    fn clone_at(path: &Path, url: &str) {
+        init_repo(path);
+        git(path, &["remote", "add", "origin", url]);
+    }
+
+    #[test]
+    fn doctor_hosts() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut config = test_config(dir.path());
+        let mut report: Report = Default::default();
+        check_hosts(&config, &mut report);
+        assert_eq!(report.problems, 0);
+        config.repos.get_mut("default").unwrap().hosts = vec!["GitHub.com".to_owned()];
+        check_hosts(&config, &mut report);
+        assert_eq!(report.problems, 1);
+    }
+
+    #[test]
+    fn doctor_roots() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        let root = config.repos["default"].root.clone();
+        let failed = dir.path().join("a/github.com/o/failed");
+        let mine = dir.path().join("a/github.com/empty");
+        fs::create_dir_all(&failed).unwrap();
+        fs::create_dir_all(&mine).unwrap();
+        init_repo(&dir.path().join("a/github.com/o/broken"));
+        fs::remove_file(dir.path().join("a/github.com/o/broken/.git/HEAD")).unwrap();
+
+        let mut report: Report = Default::default();
+        let repos = check_root(&config, &root, true, &mut report).unwrap();
+        assert_eq!(repos.len(), 1);
+        assert_eq!((report.problems, report.fixed), (2, 1));
+        assert!(!failed.exists());
+        // not where a clone goes, so not a failed clone
+        assert!(mine.exists());
+
+        let mut report: Report = Default::default();
+        let missing = dir.path().join("missing").to_string_lossy().into_owned();
+        assert!(check_root(&config, &missing, false, &mut report)
+            .unwrap()
+            .is_empty());
+        assert_eq!(report.problems, 1);
+    }
+
+    #[test]
+    fn doctor_remotes() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = test_config(dir.path());
+        clone_at(
+            &dir.path().join("b/github.com/o/r"),
+            "https://github.com/o/r",
+        );
+        clone_at(
+            &dir.path().join("a/example.com/o/r"),
+            "https://example.com/o/r",
+        );
+        let mut report: Report = Default::default();
+        let mut repos =
+            check_root(&config, &config.repos["default"].root, false, &mut report).unwrap();
+        repos.extend(check_root(&config, &config.repos["work"].root, false, &mut report).unwrap());
+        check_remotes(&config, &repos, &mut report);
+        assert_eq!(report.problems, 0);
+
+        // a rename and a second clone of the same remote
+        clone_at(
+            &dir.path().join("b/github.com/o/old"),
+            "https://github.com/o/new",
+        );
+        clone_at(
+            &dir.path().join("a/github.com/o/r"),
+            "https://github.com/o/r",
+        );
+        let mut repos =
+            check_root(&config, &config.repos["default"].root, false, &mut report).unwrap();
+        repos.extend(check_root(&config, &config.repos["work"].root, false, &mut report).unwrap());
+        check_remotes(&config, &repos, &mut report);
+        assert_eq!(report.problems, 2);
+    }
+}
@@ -0,0 +1,207 @@
+use crate::config::Config;
+use crate::local::LocalRepository;
+use crate::tag::Tags;
+use crate::vcs::VCSBackend;
+use anyhow::{Context, Result};
+use log::debug;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+///
+/// Structured filters shared by the bulk subcommands.
+/// Every given condition must match.
+///
+#[derive(Debug, Clone, Default)]
+pub struct Filter<'a> {
+    pub hosts: Vec<&'a str>,
+    pub owners: Vec<&'a str>,
+    pub vcs: Option<VCSBackend>,
+    pub dirty: bool,
+    pub stale: Option<Duration>,
+    pub tags: Vec<&'a str>,
+}
+
+/// Parses a duration like `90d`, `2w`, `12h`, `30m` or `45s`.
+pub fn parse_duration(s: &str) -> Result<Duration> {
+    let s = s.trim();
+    let pos = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (num, unit) = s.split_at(pos);
+    let num: u64 = num
+        .parse()
+        .with_context(|| format!("invalid duration {}", s))?;
+    let secs = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "" | "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return Err(anyhow::format_err!("invalid duration unit {}", unit)),
+    };
+    let secs = num
+        .checked_mul(secs)
+        .with_context(|| format!("duration too long {}", s))?;
+    Ok(Duration::from_secs(secs))
+}
+
+/// Returns true if `owner` is `filter` or one of its subgroups.
+fn match_owner(owner: &str, filter: &str) -> bool {
+    let filter = filter.trim_matches('/');
+    owner.eq_ignore_ascii_case(filter)
+        || (owner.len() > filter.len()
+            && owner.as_bytes()[filter.len()] == b'/'
+            && owner[..filter.len()].eq_ignore_ascii_case(filter))
+}
+
+impl Filter<'_> {
+    pub fn matches(&self, config: &Config<'_>, repo: &LocalRepository, tags: &Tags) -> bool {
+        if let Some(vcs) = &self.vcs {
+            if &repo.backend != vcs {
+                return false;
+            }
+        }
+        if !self.tags.is_empty() && !tags.has_any(config, repo, &self.tags) {
+            return false;
+        }
+        if !self.hosts.is_empty() || !self.owners.is_empty() {
+            let (host, path) = repo.host_and_path_in(repo.layout(config));
+            if !self.hosts.is_empty() {
+                let host = host.unwrap_or_default();
+                if !self.hosts.iter().any(|h| host.eq_ignore_ascii_case(h)) {
+                    return false;
+                }
+            }
+            if !self.owners.is_empty() {
+                let owner = path.rsplit_once('/').map(|(o, _)| o).unwrap_or("");
+                if !self.owners.iter().any(|o| match_owner(owner, o)) {
+                    return false;
+                }
+            }
+        }
+        // the following conditions run the vcs command
+        if self.dirty {
+            match repo.backend.is_dirty(&repo.path) {
+                Ok(true) => {}
+                Ok(false) => return false,
+                Err(e) => {
+                    debug!("{} path:{:?}", e, repo.path);
+                    return false;
+                }
+            }
+        }
+        if let Some(stale) = self.stale {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0);
+            match repo.backend.last_commit_time(&repo.path) {
+                Ok(Some(time)) if now.saturating_sub(time) >= stale.as_secs() => {}
+                Ok(_) => return false,
+                Err(e) => {
+                    debug!("{} path:{:?}", e, repo.path);
+                    return false;
+                }
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn duration() {
+        assert_eq!(parse_duration("90d").unwrap().as_secs(), 90 * 86400);
+        assert_eq!(parse_duration("2w").unwrap().as_secs(), 14 * 86400);
+        assert_eq!(parse_duration("12h").unwrap().as_secs(), 12 * 3600);
+        assert_eq!(parse_duration("7").unwrap().as_secs(), 7 * 86400);
+        assert!(parse_duration("d").is_err());
+        assert!(parse_duration("3y").is_err());
+        assert!(parse_duration("99999999999999999d").is_err());
+    }
+
+    #[test]
+    fn filter_host_owner_vcs() {
+        let mut config = Config {
+            meta_path: PathBuf::from("/nonexistent/rrc-meta.toml"),
+            ..Default::default()
+        };
+        config
+            .groups
+            .insert("team".to_owned(), vec!["*/myorg/team/*".to_owned()]);
+        let tags = Tags::load(&config).unwrap();
+        let repo = LocalRepository {
+            path: PathBuf::from("/repos/gitlab.example.com/myorg/team/api"),
+            relpath: PathBuf::from("gitlab.example.com/myorg/team/api"),
+            backend: VCSBackend::GitBackend,
+            url: None,
+        };
+        let filter = Filter {
+            hosts: vec!["gitlab.example.com"],
+            owners: vec!["myorg"],
+            ..Default::default()
+        };
+        assert!(filter.matches(&config, &repo, &tags));
+        let filter = Filter {
+            owners: vec!["myorg/team"],
+            vcs: Some(VCSBackend::GitBackend),
+            ..Default::default()
+        };
+        assert!(filter.matches(&config, &repo, &tags));
+        let filter = Filter {
+            owners: vec!["myor"],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&config, &repo, &tags));
+        let filter = Filter {
+            hosts: vec!["github.com"],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&config, &repo, &tags));
+        let filter = Filter {
+            vcs: Some(VCSBackend::MercurialBackend),
+            ..Default::default()
+        };
+        assert!(!filter.matches(&config, &repo, &tags));
+        let filter = Filter {
+            tags: vec!["team"],
+            ..Default::default()
+        };
+        assert!(filter.matches(&config, &repo, &tags));
+        let filter = Filter {
+            tags: vec!["backend"],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&config, &repo, &tags));
+    }
+
+    #[test]
+    fn filter_in_layout() {
+        let mut config = Config {
+            meta_path: PathBuf::from("/nonexistent/rrc-meta.toml"),
+            ..Default::default()
+        };
+        let profile = config.repos.get_mut("default").unwrap();
+        profile.root = "/repos".to_owned();
+        profile.layout = "src/{host}/{owner}/{repo}".to_owned();
+        let tags = Tags::load(&config).unwrap();
+        let repo = LocalRepository {
+            path: PathBuf::from("/repos/src/gitlab.example.com/myorg/team/api"),
+            relpath: PathBuf::from("src/gitlab.example.com/myorg/team/api"),
+            backend: VCSBackend::GitBackend,
+            url: None,
+        };
+        let filter = Filter {
+            hosts: vec!["gitlab.example.com"],
+            owners: vec!["myorg/team"],
+            ..Default::default()
+        };
+        assert!(filter.matches(&config, &repo, &tags));
+        let filter = Filter {
+            hosts: vec!["src"],
+            ..Default::default()
+        };
+        assert!(!filter.matches(&config, &repo, &tags));
+    }
+}
@@ -0,0 +1,231 @@
+use super::Repository;
+use crate::config::Config;
+use anyhow::Result;
+use log::debug;
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use std::env;
+use url::Url;
+
+/// The `api_url` of the host, or `default`.
+pub fn api_url(config: &Config<'_>, host: &str, default: String) -> String {
+    let url = config
+        .host_config(host)
+        .and_then(|conf| conf.api_url.clone())
+        .unwrap_or(default);
+    url.trim_end_matches('/').to_owned()
+}
+
+/// Owner, group and host names that are safe to put in an api url.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['-', '.'])
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+}
+
+///
+/// The `token` of the host. The environment variables are only for the public
+/// hosts of the forge, not to be sent to another server.
+///
+pub fn token(config: &Config<'_>, host: &str, public: bool, env_names: &[&str]) -> Option<String> {
+    let conf = config.host_config(host);
+    let custom_api = conf.map(|conf| conf.api_url.is_some()).unwrap_or(false);
+    conf.and_then(|conf| conf.token.clone())
+        .or_else(|| {
+            if public && !custom_api {
+                env_names.iter().find_map(|name| env::var(name).ok())
+            } else {
+                None
+            }
+        })
+        .filter(|token| !token.is_empty())
+}
+
+/// The `rel="next"` url of a `Link` header.
+fn next_link(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        if params.split(';').any(|p| p.trim() == "rel=\"next\"") {
+            let url = url.trim().strip_prefix('<')?.strip_suffix('>')?;
+            Some(url.to_owned())
+        } else {
+            None
+        }
+    })
+}
+
+/// Fails unless the `next` page is on the server of `base`, which the token is meant for.
+pub fn check_next(base: &str, next: &str) -> Result<()> {
+    let origin = |url: &str| {
+        Url::parse(url).ok().map(|url| {
+            (
+                url.scheme().to_owned(),
+                url.host_str().map(str::to_ascii_lowercase),
+                url.port_or_known_default(),
+            )
+        })
+    };
+    match (origin(base), origin(next)) {
+        (Some(base), Some(next)) if base == next => Ok(()),
+        _ => Err(anyhow::format_err!(
+            "next page {} is not on the server of {}",
+            next,
+            base
+        )),
+    }
+}
+
+/// GETs `url`, `None` if it is not found.
+fn request(url: &str, headers: &[(&str, &str)]) -> Result<Option<ureq::Response>> {
+    debug!("GET {}", url);
+    let mut req = ureq::get(url).set("User-Agent", concat!("rrc/", env!("CARGO_PKG_VERSION")));
+    for (name, value) in headers {
+        req = req.set(name, value);
+    }
+    match req.call() {
+        Ok(res) => Ok(Some(res)),
+        Err(ureq::Error::Status(404, _)) => Ok(None),
+        Err(ureq::Error::Status(code, res)) => {
+            let body = res.into_string().unwrap_or_default();
+            Err(anyhow::format_err!("{} returned {}: {}", url, code, body))
+        }
+        Err(e) => Err(anyhow::format_err!("failed request {}: {}", url, e)),
+    }
+}
+
+/// Reads JSON from `url`, `None` if it is not found.
+pub fn fetch_json<T: DeserializeOwned>(url: &str, headers: &[(&str, &str)]) -> Result<Option<T>> {
+    Ok(fetch(url, headers)?.map(|(value, _)| value))
+}
+
+/// Reads JSON and the next page in the `Link` header.
+fn fetch<T: DeserializeOwned>(
+    url: &str,
+    headers: &[(&str, &str)],
+) -> Result<Option<(T, Option<String>)>> {
+    let res = match request(url, headers)? {
+        Some(res) => res,
+        None => return Ok(None),
+    };
+    let next = res.header("Link").and_then(next_link);
+    let body = res.into_string()?;
+    let value = serde_json::from_str(&body)
+        .map_err(|e| anyhow::format_err!("failed parse response of {}: {}", url, e))?;
+    Ok(Some((value, next)))
+}
+
+///
+/// Reads a JSON array from `url` and the pages following it by the `Link` header.
+/// `None` if the first page is not found.
+///
+pub fn fetch_pages<T: DeserializeOwned>(
+    url: &str,
+    headers: &[(&str, &str)],
+) -> Result<Option<Vec<T>>> {
+    let mut items = vec![];
+    let mut next = Some(url.to_owned());
+    let mut first = true;
+    while let Some(url) = next {
+        let (page, next_url): (Vec<T>, _) = match fetch(&url, headers)? {
+            Some(page) => page,
+            None if first => return Ok(None),
+            None => return Err(anyhow::format_err!("{} not found", url)),
+        };
+        first = false;
+        items.extend(page);
+        if let Some(next_url) = &next_url {
+            check_next(&url, next_url)?;
+        }
+        next = next_url;
+    }
+    Ok(Some(items))
+}
+
+#[derive(Deserialize)]
+struct User {
+    login: String,
+}
+
+///
+/// Lists the repositories of `orgs/{owner}`, or of `users/{owner}` if there is no such organization.
+/// `users/{owner}` has only the public repositories, so the repositories of the
+/// authenticated user are listed by `user/repos`.
+///
+pub fn list_org_or_user(
+    api_url: &str,
+    owner: &str,
+    query: &str,
+    headers: &[(&str, &str)],
+    authenticated: bool,
+) -> Result<Vec<Repository>> {
+    let url = format!("{}/orgs/{}/repos?{}", api_url, owner, query);
+    if let Some(repos) = fetch_pages(&url, headers)? {
+        return Ok(repos);
+    }
+    let user: Option<User> = if authenticated {
+        fetch_json(&format!("{}/user", api_url), headers)?
+    } else {
+        None
+    };
+    let url = match user {
+        Some(user) if user.login.eq_ignore_ascii_case(owner) => {
+            format!("{}/user/repos?affiliation=owner&{}", api_url, query)
+        }
+        _ => format!("{}/users/{}/repos?{}", api_url, owner, query),
+    };
+    fetch_pages(&url, headers)?.ok_or_else(|| anyhow::format_err!("{} not found", owner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn env_token() {
+        let mut config: Config = Default::default();
+        config.host_configs.insert(
+            "ghe.example.com".to_owned(),
+            crate::config::HostConfig {
+                api_url: Some("https://api.example.com".to_owned()),
+                ..Default::default()
+            },
+        );
+        let env = &["RRC_TEST_FORGE_TOKEN"];
+        env::set_var(env[0], "secret");
+        assert_eq!(
+            token(&config, "github.com", true, env).as_deref(),
+            Some("secret")
+        );
+        assert_eq!(token(&config, "git.example.com", false, env), None);
+        // a custom api is another server
+        assert_eq!(token(&config, "ghe.example.com", true, env), None);
+        env::remove_var(env[0]);
+    }
+
+    #[test]
+    fn next_page_origin() {
+        let base = "https://api.github.com/orgs/x/repos?per_page=100";
+        assert!(check_next(base, "https://api.github.com/orgs/x/repos?page=2").is_ok());
+        assert!(check_next(base, "https://API.github.com:443/orgs/x/repos?page=2").is_ok());
+        assert!(check_next(base, "http://api.github.com/orgs/x/repos?page=2").is_err());
+        assert!(check_next(base, "https://evil.example.com/x").is_err());
+        assert!(check_next(base, "https://api.github.com:8443/x").is_err());
+        assert!(check_next(base, "/relative").is_err());
+    }
+
+    #[test]
+    fn link_header() {
+        assert_eq!(
+            next_link(
+                r#"<https://api.github.com/x?page=2>; rel="next", <https://api.github.com/x?page=5>; rel="last""#
+            ),
+            Some("https://api.github.com/x?page=2".to_owned())
+        );
+        assert_eq!(
+            next_link(r#"<https://api.github.com/x?page=1>; rel="prev""#),
+            None
+        );
+    }
+}
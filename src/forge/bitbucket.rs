@@ -0,0 +1,130 @@
+use super::{api, Repository};
+use anyhow::Result;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+pub const HOST: &str = "bitbucket.org";
+pub const TOKEN_ENV: &[&str] = &["BITBUCKET_TOKEN"];
+const API_URL: &str = "https://api.bitbucket.org/2.0";
+const PER_PAGE: usize = 100;
+
+/// A page of the Bitbucket Cloud api, the next page is linked in the body.
+#[derive(Debug, Deserialize)]
+struct Page {
+    values: Vec<BitbucketRepository>,
+    next: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct BitbucketRepository {
+    full_name: String,
+    links: Links,
+    #[serde(default)]
+    parent: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Links {
+    #[serde(default)]
+    clone: Vec<Link>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Link {
+    name: String,
+    href: String,
+}
+
+pub fn default_api_url(host: &str) -> String {
+    if host.eq_ignore_ascii_case(HOST) {
+        API_URL.to_owned()
+    } else {
+        format!("https://api.{}/2.0", host)
+    }
+}
+
+/// Lists the repositories of a workspace. Bitbucket has no archived repositories.
+pub fn list(api_url: &str, workspace: &str, token: Option<&str>) -> Result<Vec<Repository>> {
+    let auth = token.map(|token| format!("Bearer {}", token));
+    let mut headers = vec![("Accept", "application/json")];
+    if let Some(auth) = &auth {
+        headers.push(("Authorization", auth));
+    }
+    let mut repos = vec![];
+    let mut next = Some(format!(
+        "{}/repositories/{}?pagelen={}",
+        api_url, workspace, PER_PAGE
+    ));
+    while let Some(url) = next {
+        let page: Page = api::fetch_json(&url, &headers)?
+            .ok_or_else(|| anyhow::format_err!("workspace {} not found", workspace))?;
+        for repo in page.values {
+            let clone_url = match repo.links.clone.into_iter().find(|l| l.name == "https") {
+                Some(link) => link.href,
+                None => continue,
+            };
+            repos.push(Repository {
+                full_name: repo.full_name,
+                clone_url,
+                archived: false,
+                fork: repo.parent.is_some(),
+            });
+        }
+        if let Some(next) = &page.next {
+            api::check_next(api_url, next)?;
+        }
+        next = page.next;
+    }
+    Ok(repos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::mock::serve;
+
+    /// Bitbucket links its clone urls and has a parent for forks.
+    fn workspace_repo_json(name: &str, fork: bool) -> String {
+        format!(
+            r#"{{"full_name":"ws/{0}","links":{{"clone":[{{"name":"https","href":"https://me@bitbucket.org/ws/{0}.git"}},{{"name":"ssh","href":"git@bitbucket.org:ws/{0}.git"}}]}}{1}}}"#,
+            name,
+            if fork {
+                r#","parent":{"full_name":"up/x"}"#
+            } else {
+                ""
+            }
+        )
+    }
+
+    #[test]
+    fn list_workspace_repositories() {
+        let (base, handle) = serve("/2.0", "Authorization", 2, |base, url| {
+            let body = if url.ends_with("page=2") {
+                format!(r#"{{"values":[{}]}}"#, workspace_repo_json("b", true))
+            } else {
+                format!(
+                    r#"{{"values":[{}],"next":"{}/repositories/ws?pagelen=100&page=2"}}"#,
+                    workspace_repo_json("a", false),
+                    base
+                )
+            };
+            (200, body, None)
+        });
+
+        let repos = list(&base, "ws", None).unwrap();
+        let urls: Vec<&str> = repos.iter().map(|r| r.clone_url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://me@bitbucket.org/ws/a.git",
+                "https://me@bitbucket.org/ws/b.git"
+            ]
+        );
+        assert!(!repos[0].fork);
+        assert!(repos[1].fork);
+        let seen = handle.join().unwrap();
+        assert_eq!(seen[0].0, "/2.0/repositories/ws?pagelen=100");
+        assert_eq!(seen[1].0, "/2.0/repositories/ws?pagelen=100&page=2");
+        assert!(seen.iter().all(|(_, auth)| auth.is_none()));
+    }
+}
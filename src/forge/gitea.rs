@@ -0,0 +1,47 @@
+use super::{api, Repository};
+use anyhow::Result;
+
+/// Gitea and Forgejo share the api.
+pub const HOST: &str = "gitea.com";
+pub const TOKEN_ENV: &[&str] = &["GITEA_TOKEN", "FORGEJO_TOKEN"];
+/// the default maximum page size of Gitea
+const PER_PAGE: usize = 50;
+
+pub fn default_api_url(host: &str) -> String {
+    format!("https://{}/api/v1", host)
+}
+
+/// Lists the repositories of an organization, or of a user if there is no such organization.
+pub fn list(api_url: &str, owner: &str, token: Option<&str>) -> Result<Vec<Repository>> {
+    let auth = token.map(|token| format!("token {}", token));
+    let mut headers = vec![("Accept", "application/json")];
+    if let Some(auth) = &auth {
+        headers.push(("Authorization", auth));
+    }
+    let query = format!("limit={}", PER_PAGE);
+    api::list_org_or_user(api_url, owner, &query, &headers, token.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::mock::{repo_json, serve};
+
+    #[test]
+    fn list_org_repositories() {
+        let (base, handle) = serve("/api/v1", "Authorization", 1, |_, _| {
+            (
+                200,
+                format!("[{}]", repo_json("team/a", false, false)),
+                None,
+            )
+        });
+
+        let repos = list(&base, "team", Some("secret")).unwrap();
+        assert_eq!(repos.len(), 1);
+        assert_eq!(repos[0].clone_url, "https://example.com/team/a.git");
+        let seen = handle.join().unwrap();
+        assert_eq!(seen[0].0, "/api/v1/orgs/team/repos?limit=50");
+        assert_eq!(seen[0].1.as_deref(), Some("token secret"));
+    }
+}
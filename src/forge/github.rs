@@ -0,0 +1,84 @@
+use super::{api, Repository};
+use anyhow::Result;
+
+pub const HOST: &str = "github.com";
+pub const TOKEN_ENV: &[&str] = &["GITHUB_TOKEN", "GH_TOKEN"];
+const API_URL: &str = "https://api.github.com";
+const PER_PAGE: usize = 100;
+
+/// api.github.com or the api of GitHub Enterprise.
+pub fn default_api_url(host: &str) -> String {
+    if host.eq_ignore_ascii_case(HOST) {
+        API_URL.to_owned()
+    } else {
+        format!("https://{}/api/v3", host)
+    }
+}
+
+/// Lists the repositories of an organization, or of a user if there is no such organization.
+pub fn list(api_url: &str, owner: &str, token: Option<&str>) -> Result<Vec<Repository>> {
+    let auth = token.map(|token| format!("Bearer {}", token));
+    let mut headers = vec![("Accept", "application/vnd.github+json")];
+    if let Some(auth) = &auth {
+        headers.push(("Authorization", auth));
+    }
+    let query = format!("per_page={}", PER_PAGE);
+    api::list_org_or_user(api_url, owner, &query, &headers, token.is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::mock::{repo_json, serve};
+    use crate::forge::RepoFilter;
+
+    #[test]
+    fn list_user_repositories() {
+        let (base, handle) = serve("", "Authorization", 4, |base, url| {
+            if url.starts_with("/orgs/") {
+                (404, "{\"message\":\"Not Found\"}".to_owned(), None)
+            } else if url == "/user" {
+                (200, "{\"login\":\"Me\"}".to_owned(), None)
+            } else if url.ends_with("page=2") {
+                (200, format!("[{}]", repo_json("me/c", false, true)), None)
+            } else {
+                let next = format!(
+                    "<{}/user/repos?affiliation=owner&per_page=100&page=2>; rel=\"next\"",
+                    base
+                );
+                let body = format!(
+                    "[{},{}]",
+                    repo_json("me/a", false, false),
+                    repo_json("me/b", true, false)
+                );
+                (200, body, Some(next))
+            }
+        });
+
+        let repos = list(&base, "me", Some("secret")).unwrap();
+        let names: Vec<&str> = repos.iter().map(|r| r.full_name.as_str()).collect();
+        assert_eq!(names, vec!["me/a", "me/b", "me/c"]);
+        let seen = handle.join().unwrap();
+        assert_eq!(seen[0].0, "/orgs/me/repos?per_page=100");
+        assert_eq!(seen[1].0, "/user");
+        assert_eq!(seen[2].0, "/user/repos?affiliation=owner&per_page=100");
+        assert_eq!(
+            seen[3].0,
+            "/user/repos?affiliation=owner&per_page=100&page=2"
+        );
+        assert!(seen
+            .iter()
+            .all(|(_, auth)| auth.as_deref() == Some("Bearer secret")));
+
+        let filter = RepoFilter {
+            archived: false,
+            forks: false,
+        };
+        let kept: Vec<&str> = repos
+            .iter()
+            .filter(|r| filter.matches(r))
+            .map(|r| r.full_name.as_str())
+            .collect();
+        assert_eq!(kept, vec!["me/a"]);
+    }
+}
@@ -0,0 +1,88 @@
+use super::{api, RepoFilter, Repository};
+use anyhow::Result;
+use serde_derive::Deserialize;
+use serde_json::Value;
+
+pub const HOST: &str = "gitlab.com";
+pub const TOKEN_ENV: &[&str] = &["GITLAB_TOKEN"];
+const PER_PAGE: usize = 100;
+
+/// A project of the GitLab REST API.
+#[derive(Debug, Deserialize)]
+struct Project {
+    path_with_namespace: String,
+    http_url_to_repo: String,
+    #[serde(default)]
+    archived: bool,
+    #[serde(default)]
+    forked_from_project: Option<Value>,
+}
+
+pub fn default_api_url(host: &str) -> String {
+    format!("https://{}/api/v4", host)
+}
+
+/// Lists the projects of a group and of all its subgroups.
+pub fn list(
+    api_url: &str,
+    group: &str,
+    token: Option<&str>,
+    filter: RepoFilter,
+) -> Result<Vec<Repository>> {
+    let mut url = format!(
+        "{}/groups/{}/projects?include_subgroups=true&per_page={}&order_by=path&sort=asc",
+        api_url,
+        group.replace('/', "%2F"),
+        PER_PAGE
+    );
+    if !filter.archived {
+        url.push_str("&archived=false");
+    }
+    let mut headers = vec![];
+    if let Some(token) = token {
+        headers.push(("PRIVATE-TOKEN", token));
+    }
+    let projects: Vec<Project> = api::fetch_pages(&url, &headers)?
+        .ok_or_else(|| anyhow::format_err!("group {} not found", group))?;
+    Ok(projects
+        .into_iter()
+        .map(|project| Repository {
+            full_name: project.path_with_namespace,
+            clone_url: project.http_url_to_repo,
+            archived: project.archived,
+            fork: project.forked_from_project.is_some(),
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forge::mock::serve;
+
+    #[test]
+    fn list_group_projects() {
+        let (base, handle) = serve("/api/v4", "PRIVATE-TOKEN", 1, |_, _| {
+            let body = r#"[
+                {"path_with_namespace":"g/sub/a","http_url_to_repo":"https://gitlab.example.com/g/sub/a.git","archived":false},
+                {"path_with_namespace":"g/sub/b","http_url_to_repo":"https://gitlab.example.com/g/sub/b.git","archived":false,"forked_from_project":{"id":1}}
+            ]"#;
+            (200, body.to_owned(), None)
+        });
+
+        let filter = RepoFilter {
+            archived: false,
+            forks: true,
+        };
+        let projects = list(&base, "g/sub", Some("secret"), filter).unwrap();
+        let names: Vec<&str> = projects.iter().map(|p| p.full_name.as_str()).collect();
+        assert_eq!(names, vec!["g/sub/a", "g/sub/b"]);
+        assert!(projects[1].fork);
+        let seen = handle.join().unwrap();
+        assert_eq!(
+            seen[0].0,
+            "/api/v4/groups/g%2Fsub/projects?include_subgroups=true&per_page=100&order_by=path&sort=asc&archived=false"
+        );
+        assert_eq!(seen[0].1.as_deref(), Some("secret"));
+    }
+}
@@ -0,0 +1,340 @@
+mod api;
+mod bitbucket;
+mod gitea;
+mod github;
+mod gitlab;
+
+use crate::config::{Config, HostKind};
+use crate::import::{import_entries, ImportEntry};
+use anyhow::{Error, Result};
+use log::debug;
+use serde_derive::Deserialize;
+use std::str::FromStr;
+
+/// A repository listed by a forge.
+#[derive(Debug, Deserialize)]
+pub struct Repository {
+    pub full_name: String,
+    pub clone_url: String,
+    #[serde(default)]
+    pub archived: bool,
+    #[serde(default)]
+    pub fork: bool,
+}
+
+/// Which repositories of an owner are imported.
+#[derive(Debug, Clone, Copy)]
+pub struct RepoFilter {
+    pub archived: bool,
+    pub forks: bool,
+}
+
+impl RepoFilter {
+    pub fn matches(&self, repo: &Repository) -> bool {
+        (self.archived || !repo.archived) && (self.forks || !repo.fork)
+    }
+}
+
+/// Hosting services whose repositories can be listed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Forge {
+    Github,
+    Gitlab,
+    Gitea,
+    Bitbucket,
+}
+
+impl Forge {
+    /// The forge of a host kind, if it has an api.
+    pub fn of(kind: HostKind) -> Option<Forge> {
+        match kind {
+            HostKind::Github => Some(Forge::Github),
+            HostKind::Gitlab => Some(Forge::Gitlab),
+            HostKind::Gitea => Some(Forge::Gitea),
+            HostKind::Bitbucket => Some(Forge::Bitbucket),
+            HostKind::Azure | HostKind::Generic => None,
+        }
+    }
+    pub fn default_host(&self) -> &'static str {
+        match self {
+            Forge::Github => github::HOST,
+            Forge::Gitlab => gitlab::HOST,
+            Forge::Gitea => gitea::HOST,
+            Forge::Bitbucket => bitbucket::HOST,
+        }
+    }
+    pub fn default_api_url(&self, host: &str) -> String {
+        match self {
+            Forge::Github => github::default_api_url(host),
+            Forge::Gitlab => gitlab::default_api_url(host),
+            Forge::Gitea => gitea::default_api_url(host),
+            Forge::Bitbucket => bitbucket::default_api_url(host),
+        }
+    }
+    /// Hosts of the service itself, where the token of the environment is sent.
+    pub fn is_public_host(&self, host: &str) -> bool {
+        let public: &[&str] = match self {
+            Forge::Gitea => &[gitea::HOST, "codeberg.org"],
+            _ => &[self.default_host()],
+        };
+        public
+            .iter()
+            .any(|public| public.eq_ignore_ascii_case(host))
+    }
+    pub fn token_env(&self) -> &'static [&'static str] {
+        match self {
+            Forge::Github => github::TOKEN_ENV,
+            Forge::Gitlab => gitlab::TOKEN_ENV,
+            Forge::Gitea => gitea::TOKEN_ENV,
+            Forge::Bitbucket => bitbucket::TOKEN_ENV,
+        }
+    }
+    /// Lists the repositories of a user, organization, group or workspace.
+    pub fn list(
+        &self,
+        api_url: &str,
+        owner: &str,
+        token: Option<&str>,
+        filter: RepoFilter,
+    ) -> Result<Vec<Repository>> {
+        match self {
+            Forge::Github => github::list(api_url, owner, token),
+            Forge::Gitlab => gitlab::list(api_url, owner, token, filter),
+            Forge::Gitea => gitea::list(api_url, owner, token),
+            Forge::Bitbucket => bitbucket::list(api_url, owner, token),
+        }
+    }
+    /// GitLab groups may be nested, other owners are a single name.
+    fn check_owner(&self, owner: &str) -> Result<()> {
+        let valid = match self {
+            Forge::Gitlab => owner.split('/').all(api::is_valid_name),
+            _ => api::is_valid_name(owner),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(anyhow::format_err!("invalid owner {}", owner))
+        }
+    }
+}
+
+impl FromStr for Forge {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Forge> {
+        match s {
+            "github" => Ok(Forge::Github),
+            "gitlab" => Ok(Forge::Gitlab),
+            "gitea" | "forgejo" => Ok(Forge::Gitea),
+            "bitbucket" => Ok(Forge::Bitbucket),
+            _ => Err(anyhow::format_err!("unknown forge {}", s)),
+        }
+    }
+}
+
+///
+/// Resolves `--forge`, either a forge name (`github`, `gitlab`, `gitea`, `forgejo`,
+/// `bitbucket`) or a host whose kind is known.
+///
+pub fn resolve(config: &Config<'_>, name: &str) -> Result<(Forge, String)> {
+    if let Ok(forge) = name.parse::<Forge>() {
+        let host = match name {
+            // forgejo.org hosts no repositories
+            "forgejo" => "codeberg.org",
+            _ => forge.default_host(),
+        };
+        return Ok((forge, host.to_owned()));
+    }
+    if !api::is_valid_name(name) {
+        return Err(anyhow::format_err!("invalid forge {}", name));
+    }
+    match Forge::of(config.host_kind(name)) {
+        Some(forge) => Ok((forge, name.to_ascii_lowercase())),
+        None => Err(anyhow::format_err!(
+            "kind of {} is unknown, set kind in [host.\"{}\"]",
+            name,
+            name
+        )),
+    }
+}
+
+///
+/// Splits `[host/]owner`. The first segment is a host if it is configured or
+/// well known. GitHub owners have no dot, so any dotted segment is a host there,
+/// while GitLab groups such as `my.group/sub` may have one.
+///
+pub fn split_owner<'a>(config: &Config<'_>, forge: Forge, arg: &'a str) -> (&'a str, &'a str) {
+    match arg.split_once('/') {
+        Some((host, owner))
+            if config.host_config(host).is_some()
+                || HostKind::detect(host) != HostKind::Generic
+                || (forge == Forge::Github && host.contains('.')) =>
+        {
+            (host, owner)
+        }
+        _ => (forge.default_host(), arg),
+    }
+}
+
+/// Clones the missing repositories of `owner` on the forge at `host`.
+pub fn import(
+    config: &Config<'_>,
+    forge: Forge,
+    host: &str,
+    owner: &str,
+    filter: RepoFilter,
+    update: bool,
+    jobs: usize,
+) -> Result<()> {
+    if !api::is_valid_name(host) {
+        return Err(anyhow::format_err!("invalid host {}", host));
+    }
+    forge.check_owner(owner)?;
+    let public = forge.is_public_host(host);
+    let token = api::token(config, host, public, forge.token_env());
+    let api_url = api::api_url(config, host, forge.default_api_url(host));
+    let repos = forge.list(&api_url, owner, token.as_deref(), filter)?;
+    let total = repos.len();
+    let entries: Vec<ImportEntry> = repos
+        .into_iter()
+        .filter(|repo| {
+            let matched = filter.matches(repo);
+            if !matched {
+                debug!("filtered {}", repo.full_name);
+            }
+            matched
+        })
+        .map(|repo| ImportEntry {
+            url: repo.clone_url,
+            ..Default::default()
+        })
+        .collect();
+    println!(
+        "{} repositories of {}, {} filtered out",
+        total,
+        owner,
+        total - entries.len()
+    );
+    import_entries(config, &entries, update, jobs)?.report()
+}
+
+/// A local api for the tests of the forges.
+#[cfg(test)]
+mod mock {
+    use std::thread::{self, JoinHandle};
+    use std::time::Duration;
+    use tiny_http::{Header, Response, Server};
+
+    /// Status, body and `Link` header of a response.
+    pub type Reply = (u16, String, Option<String>);
+    /// Url and auth header of the requests served.
+    pub type Seen = JoinHandle<Vec<(String, Option<String>)>>;
+
+    ///
+    /// Answers `count` requests with `reply(base, url)`, `base` being the api url
+    /// ending with `path`. The thread returns the urls requested with the value of
+    /// the `auth` header, and panics if fewer requests come.
+    ///
+    pub fn serve(
+        path: &str,
+        auth: &'static str,
+        count: usize,
+        reply: impl Fn(&str, &str) -> Reply + Send + 'static,
+    ) -> (String, Seen) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let base = format!("http://{}{}", server.server_addr(), path);
+        let api_url = base.clone();
+        let handle = thread::spawn(move || {
+            (0..count)
+                .map(|i| {
+                    let req = server
+                        .recv_timeout(Duration::from_secs(10))
+                        .unwrap()
+                        .unwrap_or_else(|| panic!("expected {} requests, got {}", count, i));
+                    let url = req.url().to_owned();
+                    let token = req
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv(auth))
+                        .map(|h| h.value.to_string());
+                    let (status, body, link) = reply(&api_url, &url);
+                    let mut res = Response::from_string(body).with_status_code(status);
+                    if let Some(link) = link {
+                        res = res.with_header(Header::from_bytes("Link", link).unwrap());
+                    }
+                    req.respond(res).unwrap();
+                    (url, token)
+                })
+                .collect()
+        });
+        (base, handle)
+    }
+
+    /// A repository as GitHub and Gitea list it.
+    pub fn repo_json(full_name: &str, archived: bool, fork: bool) -> String {
+        format!(
+            r#"{{"full_name":"{0}","clone_url":"https://example.com/{0}.git","archived":{1},"fork":{2}}}"#,
+            full_name, archived, fork
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_forge() {
+        let mut config: Config = Default::default();
+        config.host_configs.insert(
+            "git.internal".to_owned(),
+            crate::config::HostConfig {
+                kind: Some(HostKind::Gitea),
+                ..Default::default()
+            },
+        );
+        let resolved = |name| resolve(&config, name).unwrap();
+        assert_eq!(resolved("github"), (Forge::Github, "github.com".to_owned()));
+        assert_eq!(
+            resolved("forgejo"),
+            (Forge::Gitea, "codeberg.org".to_owned())
+        );
+        assert_eq!(
+            resolved("bitbucket"),
+            (Forge::Bitbucket, "bitbucket.org".to_owned())
+        );
+        assert_eq!(
+            resolved("git.internal"),
+            (Forge::Gitea, "git.internal".to_owned())
+        );
+        assert_eq!(
+            resolved("gitlab.com"),
+            (Forge::Gitlab, "gitlab.com".to_owned())
+        );
+        assert!(resolve(&config, "example.com").is_err());
+        assert!(resolve(&config, "../x").is_err());
+
+        let split = |forge, arg| split_owner(&config, forge, arg);
+        assert_eq!(split(Forge::Github, "myorg"), ("github.com", "myorg"));
+        assert_eq!(
+            split(Forge::Github, "ghe.example.com/myorg"),
+            ("ghe.example.com", "myorg")
+        );
+        assert_eq!(split(Forge::Gitlab, "g/sub"), ("gitlab.com", "g/sub"));
+        assert_eq!(
+            split(Forge::Gitlab, "my.group/sub"),
+            ("gitlab.com", "my.group/sub")
+        );
+        assert_eq!(
+            split(Forge::Gitlab, "git.internal/platform/backend"),
+            ("git.internal", "platform/backend")
+        );
+        assert!(Forge::Github.is_public_host("GitHub.com"));
+        assert!(!Forge::Github.is_public_host("ghe.example.com"));
+        assert!(Forge::Gitea.is_public_host("codeberg.org"));
+        assert!(Forge::Gitlab.check_owner("g/sub").is_ok());
+        assert!(Forge::Gitlab.check_owner("g/../x").is_err());
+        assert!(Forge::Github.check_owner("g/sub").is_err());
+        assert!(Forge::Gitea.check_owner("-x").is_err());
+    }
+}
@@ -0,0 +1,342 @@
+use crate::config::{load_table, Config};
+use crate::utils::run_output;
+use anyhow::{Context, Result};
+use log::debug;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use toml::value::{Table, Value};
+use url::Url;
+
+pub const SOURCE: &str = "git config ghq.*";
+
+///
+/// Settings of ghq, read from git config.
+///
+///   ghq.root, ghq.user, ghq.<url>.root, ghq.<url>.vcs
+///
+#[derive(Debug, Default, PartialEq)]
+pub struct GhqConfig {
+    /// the first root is the one ghq clones into
+    pub roots: Vec<String>,
+    pub user: Option<String>,
+    /// url prefix to `root` and `vcs`
+    pub urls: BTreeMap<String, GhqUrlConfig>,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct GhqUrlConfig {
+    pub root: Option<String>,
+    pub vcs: Option<String>,
+}
+
+/// Parses the output of `git config --get-regexp ^ghq\.`.
+pub fn parse(output: &str) -> GhqConfig {
+    let mut ghq: GhqConfig = Default::default();
+    for line in output.lines() {
+        let (key, value) = match line.split_once(char::is_whitespace) {
+            Some((key, value)) => (key, value.trim()),
+            None => continue,
+        };
+        let key = match key.strip_prefix("ghq.") {
+            Some(key) => key,
+            None => continue,
+        };
+        match key {
+            "root" => ghq.roots.push(value.to_owned()),
+            "user" => ghq.user = Some(value.to_owned()),
+            _ => match key.rsplit_once('.') {
+                Some((url, "root")) => {
+                    ghq.urls.entry(url.to_owned()).or_default().root = Some(value.to_owned())
+                }
+                Some((url, "vcs")) => {
+                    ghq.urls.entry(url.to_owned()).or_default().vcs = Some(value.to_owned())
+                }
+                _ => debug!("ignore ghq setting {}", key),
+            },
+        }
+    }
+    ghq
+}
+
+/// The vcs names of ghq rrc supports.
+fn vcs_name(vcs: &str) -> Option<&'static str> {
+    match vcs {
+        "git" | "github" => Some("git"),
+        "hg" | "mercurial" => Some("hg"),
+        _ => None,
+    }
+}
+
+/// Host and path of a url prefix.
+fn url_prefix(url: &str) -> Option<(String, String)> {
+    let parsed = Url::parse(url).ok()?;
+    let host = parsed.host_str()?.to_ascii_lowercase();
+    Some((host, parsed.path().trim_matches('/').to_owned()))
+}
+
+/// `host/path/**` route pattern of a url prefix.
+fn route_pattern(host: &str, path: &str) -> String {
+    if path.is_empty() {
+        format!("{}/**", host)
+    } else {
+        format!("{}/{}/**", host, path)
+    }
+}
+
+fn profile(root: &str) -> Value {
+    let mut table = Table::new();
+    table.insert("root".to_owned(), Value::String(root.to_owned()));
+    Value::Table(table)
+}
+
+impl GhqConfig {
+    pub fn is_empty(&self) -> bool {
+        self.roots.is_empty() && self.user.is_none() && self.urls.is_empty()
+    }
+
+    ///
+    /// Converts to the rrc config. The first root becomes `[default]` and
+    /// the others `[ghq-2]`, `[ghq-3]`, ... so that they are still listed.
+    /// `ghq.<url>.root` is routed to a profile named after the host.
+    ///
+    pub fn to_table(&self) -> Table {
+        let mut table = Table::new();
+        // profile name of each root
+        let mut roots: BTreeMap<String, String> = BTreeMap::new();
+
+        let mut default = match self.roots.first() {
+            Some(root) => {
+                roots.insert(root.to_owned(), "default".to_owned());
+                profile(root)
+            }
+            None => Value::Table(Table::new()),
+        };
+        if let (Some(user), Value::Table(default)) = (&self.user, &mut default) {
+            default.insert("default_owner".to_owned(), Value::String(user.to_owned()));
+        }
+        table.insert("default".to_owned(), default);
+        for (i, root) in self.roots.iter().enumerate().skip(1) {
+            if roots.contains_key(root) {
+                continue;
+            }
+            let name = format!("ghq-{}", i + 1);
+            roots.insert(root.to_owned(), name.clone());
+            table.insert(name, profile(root));
+        }
+
+        let mut routes = vec![];
+        let mut hosts = Table::new();
+        for (url, conf) in &self.urls {
+            let (host, path) = match url_prefix(url) {
+                Some(parsed) => parsed,
+                None => {
+                    eprintln!("skip ghq.{} : invalid url", url);
+                    continue;
+                }
+            };
+            if let Some(root) = &conf.root {
+                let name = match roots.get(root) {
+                    Some(name) => name.to_owned(),
+                    None => {
+                        let mut name = host.clone();
+                        let mut n = 1;
+                        while table.contains_key(&name) {
+                            n += 1;
+                            name = format!("{}-{}", host, n);
+                        }
+                        roots.insert(root.to_owned(), name.clone());
+                        table.insert(name.clone(), profile(root));
+                        name
+                    }
+                };
+                let mut route = Table::new();
+                route.insert(
+                    "pattern".to_owned(),
+                    Value::String(route_pattern(&host, &path)),
+                );
+                route.insert("profile".to_owned(), Value::String(name));
+                routes.push(Value::Table(route));
+            }
+            if let Some(vcs) = &conf.vcs {
+                match vcs_name(vcs) {
+                    // rrc has per-host settings only
+                    Some(_) if !path.is_empty() => eprintln!(
+                        "skip ghq.{}.vcs : the vcs can only be set for the whole host {}",
+                        url, host
+                    ),
+                    Some(vcs) => {
+                        let mut host_conf = Table::new();
+                        host_conf.insert("vcs".to_owned(), Value::String(vcs.to_owned()));
+                        hosts.insert(host.clone(), Value::Table(host_conf));
+                    }
+                    None => eprintln!("skip ghq.{}.vcs : {} is not supported", url, vcs),
+                }
+            }
+        }
+        if !routes.is_empty() {
+            table.insert("route".to_owned(), Value::Array(routes));
+        }
+        if !hosts.is_empty() {
+            table.insert("host".to_owned(), Value::Table(hosts));
+        }
+        table
+    }
+}
+
+/// Reads the ghq settings from git config, `None` if there are none.
+pub fn load() -> Result<Option<Table>> {
+    let cmd = ["git", "config", "--path", "--get-regexp", r"^ghq\."];
+    let output = match run_output(&cmd, Path::new(".")) {
+        Ok(Some(output)) => output,
+        Ok(None) => return Ok(None),
+        Err(e) => {
+            debug!("failed to read git config {}", e);
+            return Ok(None);
+        }
+    };
+    let ghq = parse(&output);
+    if ghq.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(ghq.to_table()))
+}
+
+///
+/// Adds the ghq settings to `table`. Settings already in it are kept:
+/// tables are merged, routes appended and other values left as they are.
+///
+fn merge(table: &mut Table, ghq: Table, section: &str) {
+    for (key, value) in ghq {
+        let name = if section.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", section, key)
+        };
+        match (table.get_mut(&key), value) {
+            (None, value) => {
+                table.insert(key, value);
+            }
+            (Some(Value::Table(existing)), Value::Table(value)) => merge(existing, value, &name),
+            (Some(Value::Array(existing)), Value::Array(values)) => {
+                for value in values {
+                    if !existing.contains(&value) {
+                        existing.push(value);
+                    }
+                }
+            }
+            (Some(existing), value) if *existing == value => {}
+            (Some(_), value) => eprintln!("keep {} of the config, ghq has {}", name, value),
+        }
+    }
+}
+
+/// Writes the ghq settings to the rrc config file, merged into its settings.
+pub fn migrate(path: &str, dry_run: bool, force: bool) -> Result<()> {
+    let ghq = load()?.ok_or_else(|| anyhow::format_err!("no ghq settings in git config"))?;
+    let exists = Path::new(path).exists();
+    let mut table = if exists {
+        let s = fs::read_to_string(path).with_context(|| format!("failed read {}", path))?;
+        toml::from_str(&s).with_context(|| format!("failed parse toml. path: {}", path))?
+    } else {
+        Table::new()
+    };
+    merge(&mut table, ghq, "");
+    load_table(Config::default(), table.clone(), path)?;
+    let content = toml::to_string(&Value::Table(table))?;
+    if dry_run {
+        print!("{}", content);
+        return Ok(());
+    }
+    if exists && !force {
+        return Err(anyhow::format_err!(
+            "{} already exists, use --force to merge the ghq settings into it (comments are not kept)",
+            path
+        ));
+    }
+    fs::write(path, content).map_err(|e| anyhow::format_err!("failed write {}: {}", path, e))?;
+    println!("wrote {}", path);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghq_settings() {
+        let output = "\
+ghq.root /home/me/ghq
+ghq.root /home/me/go/src
+ghq.user me
+ghq.https://git.example.com/.vcs hg
+ghq.https://github.com/mycompany.root /work
+ghq.https://svn.example.com/.vcs svn
+ghq.https://gitlab.com/mycompany.vcs hg
+ghq.completeuser false
+";
+        let ghq = parse(output);
+        assert_eq!(ghq.roots, vec!["/home/me/ghq", "/home/me/go/src"]);
+        assert_eq!(ghq.user.as_deref(), Some("me"));
+        assert_eq!(ghq.urls.len(), 4);
+
+        let table = ghq.to_table();
+        let s = toml::to_string(&Value::Table(table.clone())).unwrap();
+        assert_eq!(toml::from_str::<Table>(&s).unwrap(), table);
+
+        let config = load_table(Config::default(), table, SOURCE).unwrap();
+        let default = config.profile("default").unwrap();
+        assert_eq!(default.root, "/home/me/ghq");
+        assert_eq!(default.default_owner.as_deref(), Some("me"));
+        assert_eq!(config.profile("ghq-2").unwrap().root, "/home/me/go/src");
+        assert_eq!(config.profile("github.com").unwrap().root, "/work");
+        assert_eq!(config.route("github.com/mycompany/api"), Some("github.com"));
+        assert_eq!(config.route("github.com/rust-lang/rust"), None);
+        assert_eq!(
+            config.host_vcs("git.example.com"),
+            Some(crate::vcs::VCSBackend::MercurialBackend)
+        );
+        assert_eq!(config.host_vcs("svn.example.com"), None);
+        // not widened to the whole host
+        assert_eq!(config.host_vcs("gitlab.com"), None);
+    }
+
+    #[test]
+    fn merge_into_config() {
+        let mut table: Table = toml::from_str(
+            r#"
+[default]
+root = "~/src"
+
+[work]
+root = "~/work"
+
+[[route]]
+pattern = "github.com/mycompany/**"
+profile = "work"
+
+[host."git.example.com"]
+protocol = "ssh"
+"#,
+        )
+        .unwrap();
+        let ghq = parse(
+            "ghq.root /home/me/ghq\nghq.user me\nghq.https://git.example.com/.vcs hg\nghq.https://gitlab.com/team.root /team\n",
+        );
+        merge(&mut table, ghq.to_table(), "");
+        let config = load_table(Config::default(), table, "rrc.toml").unwrap();
+        let default = config.profile("default").unwrap();
+        assert_eq!(default.root, "~/src");
+        assert_eq!(default.default_owner.as_deref(), Some("me"));
+        assert_eq!(config.profile("work").unwrap().root, "~/work");
+        assert_eq!(config.profile("gitlab.com").unwrap().root, "/team");
+        assert_eq!(config.route("github.com/mycompany/api"), Some("work"));
+        assert_eq!(config.route("gitlab.com/team/x"), Some("gitlab.com"));
+        let host = config.host_config("git.example.com").unwrap();
+        assert_eq!(host.protocol, Some(crate::config::Protocol::Ssh));
+        assert_eq!(
+            config.host_vcs("git.example.com"),
+            Some(crate::vcs::VCSBackend::MercurialBackend)
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use crate::config::Config;
+use crate::index::Index;
+use crate::local::find_root;
+use crate::remote::{check_collision, clone_repository, parse_url, record_clone, vcs_of};
+use crate::vcs::{VCSBackend, VCSOption};
+use anyhow::{Context, Error, Result};
+use async_std::task;
+use log::debug;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+pub const DEFAULT_JOBS: usize = 4;
+
+///
+/// A line of an import file.
+///
+///   <url> [--branch <branch>] [--vcs <git|hg>] [--profile <profile>]
+///
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ImportEntry {
+    pub url: String,
+    pub branch: Option<String>,
+    pub vcs: Option<VCSBackend>,
+    pub profile: Option<String>,
+}
+
+enum Outcome {
+    Cloned(VCSOption, VCSBackend),
+    Updated,
+    Skipped,
+}
+
+/// Outcome of an entry with its position in the input.
+type Imported = (usize, ImportEntry, Result<Outcome>);
+
+fn parse_line(line: &str) -> Result<Option<ImportEntry>> {
+    let mut tokens = line.split_whitespace().take_while(|t| !t.starts_with('#'));
+    let url = match tokens.next() {
+        Some(url) => url,
+        None => return Ok(None),
+    };
+    let mut entry = ImportEntry {
+        url: url.to_owned(),
+        ..Default::default()
+    };
+    while let Some(option) = tokens.next() {
+        let value = tokens
+            .next()
+            .with_context(|| format!("{} requires a value", option))?
+            .to_owned();
+        match option {
+            "-b" | "--branch" => entry.branch = Some(value),
+            "--vcs" => entry.vcs = Some(value.parse()?),
+            "-p" | "--profile" => entry.profile = Some(value),
+            _ => return Err(anyhow::format_err!("unknown option {}", option)),
+        }
+    }
+    Ok(Some(entry))
+}
+
+/// Parses an import file. Blank lines and `#` comments are skipped.
+pub fn parse(input: &str) -> Result<Vec<ImportEntry>> {
+    let mut entries = vec![];
+    for (i, line) in input.lines().enumerate() {
+        let entry = parse_line(line).map_err(|e| anyhow::format_err!("line {}: {}", i + 1, e))?;
+        if let Some(entry) = entry {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+fn read_input(file: Option<&str>) -> Result<String> {
+    match file {
+        Some(file) if file != "-" => {
+            fs::read_to_string(file).with_context(|| format!("failed read {}", file))
+        }
+        _ => {
+            let mut input = String::new();
+            io::stdin().read_to_string(&mut input)?;
+            Ok(input)
+        }
+    }
+}
+
+fn import_entry(
+    config: &Config<'_>,
+    entry: &ImportEntry,
+    mut opt: VCSOption,
+    update: bool,
+) -> Result<Outcome> {
+    opt.branch = entry.branch.clone();
+    let vcs = match &entry.vcs {
+        Some(vcs) => vcs.clone(),
+        None => vcs_of(config, &opt)?,
+    };
+    check_collision(config, &opt, &vcs)?;
+    if opt.path.exists() {
+        if update {
+            vcs.update(&opt)?;
+            return Ok(Outcome::Updated);
+        }
+        return Ok(Outcome::Skipped);
+    }
+    clone_repository(&opt, &vcs)?;
+    Ok(Outcome::Cloned(opt, vcs))
+}
+
+/// Result of an import.
+#[derive(Default)]
+pub struct Summary {
+    pub cloned: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    /// url and error of the entries that failed
+    pub failed: Vec<(String, Error)>,
+    /// local paths of the entries
+    pub paths: BTreeSet<PathBuf>,
+}
+
+impl Summary {
+    /// Prints the failures and the counts, and fails if an entry failed.
+    pub fn report(self) -> Result<()> {
+        for (url, e) in &self.failed {
+            eprintln!("failed {} : {}", url, e);
+        }
+        println!(
+            "cloned {}, updated {}, skipped {}, failed {}",
+            self.cloned,
+            self.updated,
+            self.skipped,
+            self.failed.len()
+        );
+        if self.failed.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow::format_err!(
+                "failed to import {} repositories",
+                self.failed.len()
+            ))
+        }
+    }
+}
+
+/// Clones the missing repositories of `entries` with `jobs` workers.
+pub fn import_entries(
+    config: &Config<'_>,
+    entries: &[ImportEntry],
+    update: bool,
+    jobs: usize,
+) -> Result<Summary> {
+    let mut summary: Summary = Default::default();
+    let mut planned = vec![];
+    for entry in entries {
+        let profile = entry.profile.as_deref().or(config.profile);
+        match parse_url(config, profile, &entry.url) {
+            Ok(opt) if !summary.paths.insert(opt.path.clone()) => {
+                debug!("duplicate {} path:{:?}", entry.url, opt.path);
+            }
+            Ok(opt) if config.dry_run => {
+                if opt.path.exists() {
+                    summary.skipped += 1;
+                } else {
+                    let url = opt.url.as_ref().context("url not found")?;
+                    println!("{} : dry-run clone {}", opt.path.display(), url);
+                    summary.cloned += 1;
+                }
+            }
+            Ok(opt) => planned.push((entry, opt)),
+            Err(e) => summary.failed.push((entry.url.to_owned(), e)),
+        }
+    }
+    if planned.is_empty() {
+        return Ok(summary);
+    }
+
+    // freshness must be checked before the clones change the roots
+    let mut index = Index::load();
+    let mut fresh = BTreeMap::new();
+    for (_, opt) in &planned {
+        if let Some(root) = find_root(config, &opt.path) {
+            let is_fresh = index.is_fresh(root);
+            fresh.entry(root).or_insert(is_fresh);
+        }
+    }
+
+    // each task takes the next entry when it is done, so a slow clone holds only one
+    let workers = jobs.max(1).min(planned.len());
+    // reversed, so that popping takes the entries in order
+    let queue: Vec<(usize, ImportEntry, VCSOption)> = planned
+        .into_iter()
+        .enumerate()
+        .rev()
+        .map(|(i, (entry, opt))| (i, entry.clone(), opt))
+        .collect();
+    let queue = Arc::new(Mutex::new(queue));
+    let shared = Arc::new(config.detach());
+    let mut futures: Vec<task::JoinHandle<Vec<Imported>>> = vec![];
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let config = Arc::clone(&shared);
+        let f = task::spawn_blocking(move || {
+            let mut results = vec![];
+            loop {
+                let next = queue.lock().unwrap().pop();
+                let (i, entry, opt) = match next {
+                    Some(next) => next,
+                    None => return results,
+                };
+                let result = import_entry(&config, &entry, opt, update);
+                results.push((i, entry, result));
+            }
+        });
+        futures.push(f);
+    }
+    let mut results = vec![];
+    for f in futures {
+        results.extend(task::block_on(f));
+    }
+    results.sort_by_key(|(i, _, _)| *i);
+
+    for (_, entry, result) in results {
+        match result {
+            Ok(Outcome::Cloned(opt, vcs)) => {
+                println!("cloned {}", opt.path.display());
+                summary.cloned += 1;
+                if let Some(root) = find_root(config, &opt.path) {
+                    let is_fresh = fresh.get(root).copied().unwrap_or(false);
+                    record_clone(&mut index, root, is_fresh, &opt, &vcs)?;
+                }
+            }
+            Ok(Outcome::Updated) => summary.updated += 1,
+            Ok(Outcome::Skipped) => {
+                debug!("exists {}", entry.url);
+                summary.skipped += 1;
+            }
+            Err(e) => summary.failed.push((entry.url.to_owned(), e)),
+        }
+    }
+    if summary.cloned > 0 {
+        index.save()?;
+    }
+    Ok(summary)
+}
+
+/// Clones the repositories listed in `file` (stdin by default).
+pub fn import(config: &Config<'_>, file: Option<&str>, update: bool, jobs: usize) -> Result<()> {
+    let entries = parse(&read_input(file)?)?;
+    import_entries(config, &entries, update, jobs)?.report()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_import_file() {
+        let input = "
+# bootstrap
+mopemope/rrc
+https://gitlab.com/foo/bar --branch develop  # pinned
+git@example.com:foo/baz --vcs git -p work
+
+";
+        let entries = parse(input).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ImportEntry {
+                    url: "mopemope/rrc".to_owned(),
+                    ..Default::default()
+                },
+                ImportEntry {
+                    url: "https://gitlab.com/foo/bar".to_owned(),
+                    branch: Some("develop".to_owned()),
+                    ..Default::default()
+                },
+                ImportEntry {
+                    url: "git@example.com:foo/baz".to_owned(),
+                    vcs: Some(VCSBackend::GitBackend),
+                    profile: Some("work".to_owned()),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert!(parse("foo/bar --branch").is_err());
+        assert!(parse("foo/bar --depth 1").is_err());
+        assert!(parse("foo/bar --vcs svn").is_err());
+    }
+}
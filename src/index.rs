@@ -0,0 +1,308 @@
+use crate::local::LocalRepository;
+use crate::utils::real_path;
+use crate::vcs::VCSBackend;
+use anyhow::{Context, Result};
+use dirs::{cache_dir, home_dir};
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use toml::{from_str, to_string};
+
+///
+/// On-disk cache of the repositories discovered under each root.
+///
+/// Every directory read while walking a root (except the repositories
+/// themselves) is recorded with its mtime. Creating or removing an entry in
+/// any of those directories changes its mtime, so a root is considered fresh
+/// as long as all recorded mtimes still match.
+///
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    #[serde(default)]
+    roots: BTreeMap<String, RootIndex>,
+    #[serde(skip)]
+    changed: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RootIndex {
+    #[serde(default)]
+    options: String,
+    // toml can not write an empty array after the tables of another one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    dirs: Vec<DirEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    repos: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct DirEntry {
+    path: StoredPath,
+    mtime: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct IndexEntry {
+    path: StoredPath,
+    relpath: StoredPath,
+    backend: String,
+    url: Option<String>,
+}
+
+/// A path stored as a string, or as raw bytes when it is not valid UTF-8.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+enum StoredPath {
+    Str(String),
+    Bytes(Vec<u8>),
+}
+
+impl From<&Path> for StoredPath {
+    fn from(path: &Path) -> StoredPath {
+        if let Some(s) = path.to_str() {
+            return StoredPath::Str(s.to_owned());
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::ffi::OsStrExt;
+            StoredPath::Bytes(path.as_os_str().as_bytes().to_vec())
+        }
+        #[cfg(not(unix))]
+        {
+            StoredPath::Str(path.to_string_lossy().into_owned())
+        }
+    }
+}
+
+impl StoredPath {
+    fn to_path(&self) -> PathBuf {
+        match self {
+            StoredPath::Str(s) => PathBuf::from(s),
+            #[cfg(unix)]
+            StoredPath::Bytes(b) => {
+                use std::ffi::OsStr;
+                use std::os::unix::ffi::OsStrExt;
+                PathBuf::from(OsStr::from_bytes(b))
+            }
+            #[cfg(not(unix))]
+            StoredPath::Bytes(b) => PathBuf::from(String::from_utf8_lossy(b).into_owned()),
+        }
+    }
+}
+
+pub fn get_index_path() -> PathBuf {
+    match env::var("RRC_INDEX") {
+        Ok(val) => PathBuf::from(val),
+        Err(_) => {
+            let cache = cache_dir().unwrap_or_else(|| home_dir().unwrap().join(".cache"));
+            cache.join("rrc").join("index.toml")
+        }
+    }
+}
+
+pub fn mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+    let elapsed = modified.duration_since(UNIX_EPOCH).ok()?;
+    Some(elapsed.as_nanos() as u64)
+}
+
+impl RootIndex {
+    fn is_fresh(&self) -> bool {
+        !self.dirs.is_empty()
+            && self
+                .dirs
+                .iter()
+                .all(|dir| mtime(&dir.path.to_path()) == Some(dir.mtime))
+    }
+}
+
+impl IndexEntry {
+    fn from_repo(repo: &LocalRepository) -> IndexEntry {
+        IndexEntry {
+            path: repo.path.as_path().into(),
+            relpath: repo.relpath.as_path().into(),
+            backend: repo.backend.name().to_owned(),
+            url: repo.url.clone(),
+        }
+    }
+
+    fn to_repo(&self) -> Option<LocalRepository> {
+        let backend: VCSBackend = self.backend.parse().ok()?;
+        Some(LocalRepository {
+            path: self.path.to_path(),
+            relpath: self.relpath.to_path(),
+            backend,
+            url: self.url.clone(),
+        })
+    }
+}
+
+impl Index {
+    pub fn load() -> Index {
+        let path = get_index_path();
+        match fs::read_to_string(&path) {
+            Ok(s) => match from_str(&s) {
+                Ok(index) => index,
+                Err(e) => {
+                    debug!("broken index {:?} {}", path, e);
+                    Default::default()
+                }
+            },
+            Err(e) => {
+                debug!("{} path:{:?}", e, path);
+                Default::default()
+            }
+        }
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if !self.changed {
+            return Ok(());
+        }
+        let path = get_index_path();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let s = to_string(self).context("failed serialize index")?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, s)?;
+        fs::rename(&tmp, &path)?;
+        debug!("saved index {:?}", path);
+        Ok(())
+    }
+
+    pub fn is_fresh(&self, root: &str) -> bool {
+        self.roots.get(root).map(|r| r.is_fresh()).unwrap_or(false)
+    }
+
+    /// Returns the cached repositories of `root` if nothing changed since it was indexed
+    /// with the same walk options.
+    pub fn repositories(&self, root: &str, options: &str) -> Option<Vec<LocalRepository>> {
+        let root_index = self.roots.get(root)?;
+        if root_index.options != options || !root_index.is_fresh() {
+            debug!("index of {} is stale", root);
+            return None;
+        }
+        Some(
+            root_index
+                .repos
+                .iter()
+                .filter_map(|e| e.to_repo())
+                .collect(),
+        )
+    }
+
+    /// Returns the remote url recorded for `path`, if any.
+    pub fn url(&self, root: &str, path: &Path) -> Option<String> {
+        let root_index = self.roots.get(root)?;
+        root_index
+            .repos
+            .iter()
+            .find(|e| e.path.to_path() == path)
+            .and_then(|e| e.url.clone())
+    }
+
+    pub fn replace(
+        &mut self,
+        root: &str,
+        options: &str,
+        dirs: BTreeMap<PathBuf, u64>,
+        repos: &[LocalRepository],
+    ) {
+        let repos = repos.iter().map(IndexEntry::from_repo).collect();
+        let dirs = dirs
+            .iter()
+            .map(|(path, mtime)| DirEntry {
+                path: path.as_path().into(),
+                mtime: *mtime,
+            })
+            .collect();
+        let options = options.to_owned();
+        self.roots.insert(
+            root.to_owned(),
+            RootIndex {
+                options,
+                dirs,
+                repos,
+            },
+        );
+        self.changed = true;
+    }
+
+    pub fn invalidate(&mut self, root: &str) {
+        if self.roots.remove(root).is_some() {
+            self.changed = true;
+        }
+    }
+
+    /// Adds a freshly cloned repository and refreshes the mtimes of its parents.
+    pub fn insert(&mut self, root: &str, repo: &LocalRepository) {
+        if let Some(root_index) = self.roots.get_mut(root) {
+            root_index.repos.retain(|e| e.path.to_path() != repo.path);
+            root_index.repos.push(IndexEntry::from_repo(repo));
+            touch_parents(root, &repo.path, &mut root_index.dirs);
+            self.changed = true;
+        }
+    }
+
+    /// Forgets a removed repository and refreshes the mtimes of its parents.
+    pub fn remove(&mut self, root: &str, path: &Path) {
+        if let Some(root_index) = self.roots.get_mut(root) {
+            root_index.repos.retain(|e| e.path.to_path() != path);
+            touch_parents(root, path, &mut root_index.dirs);
+            self.changed = true;
+        }
+    }
+}
+
+fn touch_parents(root: &str, path: &Path, dirs: &mut Vec<DirEntry>) {
+    let root = real_path(root).unwrap_or_else(|| PathBuf::from(root));
+    for parent in path.ancestors().skip(1) {
+        if !parent.starts_with(&root) {
+            break;
+        }
+        if let Some(time) = mtime(parent) {
+            dirs.retain(|d| d.path.to_path() != parent);
+            dirs.push(DirEntry {
+                path: parent.into(),
+                mtime: time,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = LocalRepository {
+            path: dir.path().join("github.com/owner/repo"),
+            relpath: PathBuf::from("github.com/owner/repo"),
+            backend: VCSBackend::GitBackend,
+            url: Some("https://github.com/owner/repo".to_owned()),
+        };
+        let mut dirs = BTreeMap::new();
+        dirs.insert(dir.path().to_path_buf(), 1);
+        let mut index: Index = Default::default();
+        index.replace("/a", "opts", dirs.clone(), &[repo]);
+        // a root without repositories
+        index.replace("/b", "opts", dirs, &[]);
+        index.replace("/c", "opts", BTreeMap::new(), &[]);
+
+        let s = to_string(&index).unwrap();
+        let loaded: Index = from_str(&s).unwrap();
+        assert_eq!(loaded.roots.len(), 3);
+        assert_eq!(loaded.roots["/a"].repos.len(), 1);
+        assert_eq!(loaded.roots["/a"].dirs.len(), 1);
+        assert!(loaded.roots["/b"].repos.is_empty());
+        assert_eq!(loaded.roots["/b"].dirs.len(), 1);
+        assert!(loaded.roots["/c"].dirs.is_empty());
+    }
+}
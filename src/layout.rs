@@ -0,0 +1,188 @@
+use anyhow::Result;
+use regex::Regex;
+
+pub const DEFAULT_LAYOUT: &str = "{host}/{path}";
+
+const VARIABLES: [&str; 4] = ["host", "owner", "repo", "path"];
+
+///
+/// Directory layout template of a profile.
+///
+/// Available variables:
+///   {host}  : repository host (github.com)
+///   {owner} : all path segments except the last one (mopemope, group/subgroup)
+///   {repo}  : the last path segment (rrc)
+///   {path}  : the whole repository path (mopemope/rrc)
+///
+pub fn validate(template: &str) -> Result<()> {
+    let mut rest = template;
+    let mut has_repo = false;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow::format_err!("unclosed variable in layout '{}'", template))?;
+        let name = &rest[start + 1..start + end];
+        if !VARIABLES.contains(&name) {
+            return Err(anyhow::format_err!(
+                "unknown variable {{{}}} in layout '{}'",
+                name,
+                template
+            ));
+        }
+        if name == "repo" || name == "path" {
+            has_repo = true;
+        }
+        rest = &rest[start + end + 1..];
+    }
+    if !has_repo {
+        return Err(anyhow::format_err!(
+            "layout '{}' must contain {{repo}} or {{path}}",
+            template
+        ));
+    }
+    Ok(())
+}
+
+/// Splits a repository path into owner and repo name.
+pub fn split_path(path: &str) -> (&str, &str) {
+    match path.rfind('/') {
+        Some(pos) => (&path[..pos], &path[pos + 1..]),
+        None => ("", path),
+    }
+}
+
+/// Renders the layout template into a path relative to the root.
+/// `.` and `..` segments are rejected so the path cannot leave the root.
+pub fn render(template: &str, host: &str, path: &str) -> Result<String> {
+    validate(template)?;
+    let (owner, repo) = split_path(path);
+    let rendered = template
+        .replace("{host}", host)
+        .replace("{owner}", owner)
+        .replace("{repo}", repo)
+        .replace("{path}", path);
+    let segments: Vec<&str> = rendered.split('/').filter(|s| !s.is_empty()).collect();
+    if segments
+        .iter()
+        .any(|s| *s == "." || *s == ".." || s.contains('\\'))
+    {
+        return Err(anyhow::format_err!(
+            "invalid repository path {}/{}",
+            host,
+            path
+        ));
+    }
+    Ok(segments.join("/"))
+}
+
+/// Returns how many directories below the root an `owner/repo` clone is.
+pub fn depth(template: &str) -> Result<usize> {
+    Ok(render(template, "host", "owner/repo")?.split('/').count())
+}
+
+/// Reads the host and the repository path back from a path rendered by the template.
+/// The host is unknown if the template has no `{host}`.
+pub fn parse(template: &str, relpath: &str) -> Option<(Option<String>, String)> {
+    validate(template).ok()?;
+    let template = template
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join("/");
+    let mut pattern = String::from("^");
+    let mut names = vec![];
+    let mut rest = template.as_str();
+    while let Some(start) = rest.find('{') {
+        let end = start + rest[start..].find('}')?;
+        let name = &rest[start + 1..end];
+        pattern.push_str(&regex::escape(&rest[..start]));
+        pattern.push_str(match name {
+            "owner" | "path" => "(.+)",
+            _ => "([^/]+)",
+        });
+        names.push(name);
+        rest = &rest[end + 1..];
+    }
+    pattern.push_str(&regex::escape(rest));
+    pattern.push('$');
+    let caps = Regex::new(&pattern).ok()?.captures(relpath)?;
+    let var = |var: &str| {
+        names
+            .iter()
+            .position(|n| *n == var)
+            .map(|i| caps[i + 1].to_owned())
+    };
+    let path = match (var("path"), var("owner"), var("repo")) {
+        (Some(path), _, _) => path,
+        (None, Some(owner), Some(repo)) => format!("{}/{}", owner, repo),
+        (None, None, Some(repo)) => repo,
+        _ => return None,
+    };
+    Some((var("host"), path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn render_layout() {
+        let path = "mopemope/rrc";
+        assert_eq!(
+            render(DEFAULT_LAYOUT, "github.com", path).unwrap(),
+            "github.com/mopemope/rrc"
+        );
+        assert_eq!(
+            render("{host}/{owner}/{repo}", "github.com", path).unwrap(),
+            "github.com/mopemope/rrc"
+        );
+        assert_eq!(
+            render("{owner}/{repo}", "github.com", path).unwrap(),
+            "mopemope/rrc"
+        );
+        assert_eq!(render("{repo}", "github.com", path).unwrap(), "rrc");
+        assert_eq!(
+            render("{host}/{owner}/{repo}", "example.com", "rrc").unwrap(),
+            "example.com/rrc"
+        );
+        assert_eq!(
+            render("{owner}/{repo}", "gitlab.com", "group/sub/project").unwrap(),
+            "group/sub/project"
+        );
+        assert_eq!(depth(DEFAULT_LAYOUT).unwrap(), 3);
+        assert_eq!(depth("{owner}-{repo}").unwrap(), 1);
+    }
+
+    #[test]
+    fn parse_layout() {
+        let parsed = |host: &str, path: &str| Some((Some(host.to_owned()), path.to_owned()));
+        assert_eq!(
+            parse(DEFAULT_LAYOUT, "github.com/mopemope/rrc"),
+            parsed("github.com", "mopemope/rrc")
+        );
+        assert_eq!(
+            parse(
+                "src/{host}/{owner}/{repo}",
+                "src/gitlab.com/group/sub/project"
+            ),
+            parsed("gitlab.com", "group/sub/project")
+        );
+        assert_eq!(
+            parse("{owner}/{repo}", "mopemope/rrc"),
+            Some((None, "mopemope/rrc".to_owned()))
+        );
+        assert_eq!(
+            parse("{host}-{owner}-{repo}", "github.com-mopemope-rrc"),
+            parsed("github.com", "mopemope/rrc")
+        );
+        assert_eq!(parse("{host}/{owner}/{repo}", "github.com"), None);
+    }
+
+    #[test]
+    fn invalid_layout() {
+        assert!(validate("{host}/{owner}").is_err());
+        assert!(validate("{host}/{name}").is_err());
+        assert!(validate("{host}/{repo").is_err());
+        assert!(validate("{owner}-{repo}").is_ok());
+    }
+}
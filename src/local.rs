@@ -1,243 +1,304 @@
-use crate::config::Config;
-use crate::utils::{chdir, confirm, run_with_work_dir};
-use crate::vcs::{detect_vcs_from_path, VCSBackend, VCSOption};
-use anyhow::{Context, Result};
-use async_std::task;
+use crate::config::{Config, ListFormat};
+use crate::index::Index;
+use crate::layout;
+use crate::remote::parse_remote;
+use crate::tag::Tags;
+use crate::utils::{chdir, confirm, real_path, run_with_work_dir};
+use crate::vcs::{VCSBackend, VCSOption};
+use crate::walk::{walk, WalkOptions};
+use anyhow::Result;
 use log::debug;
+use serde_derive::Serialize;
+use std::collections::BTreeSet;
 use std::fmt::{self, Debug, Formatter};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
 
 #[derive(Clone)]
 pub struct LocalRepository {
-    pub path: String,
-    pub relpath: String,
+    pub path: PathBuf,
+    pub relpath: PathBuf,
     pub backend: VCSBackend,
+    pub url: Option<String>,
 }
 
 impl LocalRepository {
-    pub fn as_str(&self) -> &str {
-        self.path.as_ref()
-    }
-}
-
-impl Debug for LocalRepository {
-    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        f.debug_struct("LocalRepository")
-            .field("path", &self.path)
-            .field("backend", &self.backend)
-            .finish()
-    }
-}
-
-fn find_repository(
-    root_path: Arc<String>,
-    path: &PathBuf,
-    entry: fs::DirEntry,
-) -> Result<Option<LocalRepository>> {
-    if let Some(file_name) = entry.file_name().to_str() {
-        if let Some(backend) = detect_vcs_from_path(file_name) {
-            let path = fs::canonicalize(&path)?;
-            let path = path
-                .parent()
-                .context("failed get parent")?
-                .to_str()
-                .context("failed get parent")?
-                .to_owned();
-            let relpath = path[root_path.len() + 1..].to_owned();
-            return Ok(Some(LocalRepository {
-                path,
-                relpath,
-                backend,
-            }));
+    pub fn from_option(root: &Path, opt: &VCSOption, backend: VCSBackend) -> LocalRepository {
+        let relpath = opt
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&opt.path)
+            .to_path_buf();
+        LocalRepository {
+            path: opt.path.clone(),
+            relpath,
+            backend,
+            url: opt.url.clone(),
         }
     }
-    Ok(None)
-}
 
-fn find_sub_repositories(
-    root_path: Arc<String>,
-    root: &Path,
-    repos: &mut Arc<Mutex<Vec<LocalRepository>>>,
-) -> Result<()> {
-    let res = fs::read_dir(root);
-    if let Err(e) = res {
-        debug!("{} path:{:?}", e, root);
-        return Ok(());
+    /// Returns the host and the repository path, from the remote url if known
+    /// or guessed from the default layout otherwise.
+    pub fn host_and_path(&self) -> (Option<String>, String) {
+        self.host_and_path_in(layout::DEFAULT_LAYOUT)
     }
-    let root = res?;
-    for entry in root {
-        let entry = entry?;
-        let path = entry.path();
-        let metadata = fs::metadata(&path)?;
-        if metadata.is_file() {
-            continue;
+
+    /// Like `host_and_path`, guessing from the layout of the profile.
+    pub fn host_and_path_in(&self, template: &str) -> (Option<String>, String) {
+        if let Some(Ok(remote)) = self.url.as_deref().map(parse_remote) {
+            return (Some(remote.host), remote.path);
         }
-        if let Some(repo) = find_repository(Arc::clone(&root_path), &path, entry)? {
-            let mut repos = repos.lock().unwrap();
-            repos.push(repo);
-            return Ok(());
-        } else {
-            find_sub_repositories(Arc::clone(&root_path), &path, repos)?;
+        let relpath = self.relpath.to_string_lossy().into_owned();
+        match layout::parse(template, &relpath) {
+            // a default layout path without owner is not a repository path
+            Some((Some(_), path)) if template == layout::DEFAULT_LAYOUT && !path.contains('/') => {
+                (None, relpath)
+            }
+            Some((host, path)) => (host, path),
+            None => (None, relpath),
         }
     }
-    Ok(())
-}
 
-async fn find_repositories(
-    root_path: Arc<String>,
-    root: &Path,
-    repos: &mut Arc<Mutex<Vec<LocalRepository>>>,
-) -> Result<()> {
-    let res = fs::read_dir(root);
-    if let Err(e) = res {
-        debug!("{} path:{:?}", e, root);
-        return Ok(());
+    /// Identifies the repository as `host/owner/repo` regardless of the local layout.
+    pub fn key(&self, config: &Config<'_>) -> String {
+        join_key(self.host_and_path_in(self.layout(config)))
     }
-    let root = res?;
-    for entry in root {
-        let entry = entry?;
-        let path = entry.path();
-        let metadata = fs::metadata(&path)?;
-        if metadata.is_file() {
-            continue;
-        }
-        if let Some(repo) = find_repository(Arc::clone(&root_path), &path, entry)? {
-            let mut repos = repos.lock().unwrap();
-            repos.push(repo);
-            return Ok(());
-        } else {
-            find_sub_repositories(Arc::clone(&root_path), &path, repos)?;
-        }
+
+    /// Returns the profile of the root the repository is in.
+    pub fn profile<'c>(&self, config: &'c Config<'_>) -> Option<&'c str> {
+        // routed by the default layout, which is known before the profile
+        let key = join_key(self.host_and_path());
+        find_root(config, &self.path).and_then(|root| config.profile_of(root, &key))
     }
 
-    Ok(())
+    /// Returns the layout of the profile, the default one outside the roots.
+    pub fn layout<'c>(&self, config: &'c Config<'_>) -> &'c str {
+        self.profile(config)
+            .and_then(|name| config.profile(name).ok())
+            .map(|profile| profile.layout.as_str())
+            .unwrap_or(layout::DEFAULT_LAYOUT)
+    }
 }
 
-async fn find_user_repositories(
-    root_path: Arc<String>,
-    root: &Path,
-    repos: &mut Arc<Mutex<Vec<LocalRepository>>>,
-) -> Result<()> {
-    let res = fs::read_dir(root);
-    if let Err(e) = res {
-        debug!("{} path:{:?}", e, root);
-        return Ok(());
+fn join_key((host, path): (Option<String>, String)) -> String {
+    match host {
+        Some(host) => format!("{}/{}", host, path),
+        None => path,
     }
-    let root = res?;
-    for entry in root {
-        let entry = entry?;
-        let path = entry.path();
-        let metadata = fs::metadata(&path)?;
-        if metadata.is_file() {
-            continue;
-        }
-        if let Some(repo) = find_repository(Arc::clone(&root_path), &path, entry)? {
-            let mut repos = repos.lock().unwrap();
-            repos.push(repo);
-            return Ok(());
-        }
-        find_repositories(Arc::clone(&root_path), &path, repos).await?;
-    }
-    Ok(())
 }
 
-async fn find_service_repositories(
-    root_path: Arc<String>,
-    root: &Path,
-    repos: &mut Arc<Mutex<Vec<LocalRepository>>>,
-) -> Result<()> {
-    let res = fs::read_dir(root);
-    if let Err(e) = res {
-        debug!("{} path:{:?}", e, root);
-        return Ok(());
-    }
-    let root = res?;
-    for entry in root {
-        let entry = entry?;
-        let path = entry.path();
-        let metadata = fs::metadata(&path)?;
-        if metadata.is_file() {
-            continue;
-        }
-        find_user_repositories(Arc::clone(&root_path), &path, &mut Arc::clone(&repos)).await?;
+impl Debug for LocalRepository {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_struct("LocalRepository")
+            .field("path", &self.path)
+            .field("backend", &self.backend)
+            .field("url", &self.url)
+            .finish()
     }
-
-    Ok(())
 }
 
-fn walk_repository(root_path: &str, repos: &mut Arc<Mutex<Vec<LocalRepository>>>) -> Result<()> {
-    let res = fs::read_dir(root_path);
-    if let Err(e) = res {
-        debug!("{} path:{:?}", e, root_path);
-        return Ok(());
-    }
-    let root = res?;
-    let root_path = Arc::new(root_path.to_owned());
-    let mut futures: Vec<task::JoinHandle<Result<()>>> = vec![];
-
-    for entry in root {
-        let entry = entry?;
-        let path = entry.path();
-        let metadata = fs::metadata(&path)?;
-        if metadata.is_file() {
-            continue;
+/// Returns the repositories under `root`, walking it only when the index is stale.
+fn scan_root(config: &Config<'_>, index: &mut Index, root: &str) -> Result<Vec<LocalRepository>> {
+    let opts = config.walk_options(root);
+    let mut repos = match index.repositories(root, &opts.fingerprint()) {
+        Some(repos) => {
+            debug!("use index {}", root);
+            repos
         }
-
-        let repos = Arc::clone(&repos);
-        let root_path = Arc::clone(&root_path);
-        let f = task::spawn(async move {
-            find_service_repositories(Arc::clone(&root_path), &path, &mut Arc::clone(&repos)).await
-        });
-        futures.push(f);
+        None => reindex_root(index, root, &opts, false)?,
+    };
+    // the index keeps the remote of the checkout, a mirror is known by its original url
+    for repo in repos.iter_mut() {
+        repo.url = repo.url.as_deref().map(|url| config.original_url(url));
     }
+    Ok(repos)
+}
 
-    for f in futures {
-        task::block_on(f)?;
+/// Walks `root` again. The remote urls are read from the clones if `refresh`,
+/// otherwise those of the index are kept.
+fn reindex_root(
+    index: &mut Index,
+    root: &str,
+    opts: &WalkOptions,
+    refresh: bool,
+) -> Result<Vec<LocalRepository>> {
+    debug!("walk {} {:?}", root, opts);
+    let walked = walk(root, opts)?;
+    let mut repos = walked.repos;
+    for repo in repos.iter_mut() {
+        let cached = if refresh {
+            None
+        } else {
+            index.url(root, &repo.path)
+        };
+        repo.url = match cached {
+            Some(url) => Some(url),
+            None => repo.backend.remote_url(&repo.path).unwrap_or_else(|e| {
+                debug!("{} path:{:?}", e, repo.path);
+                None
+            }),
+        };
     }
-
-    Ok(())
+    index.replace(root, &opts.fingerprint(), walked.dirs, &repos);
+    Ok(repos)
 }
 
-fn walk_repositories(config: &Config<'_>) -> Result<Vec<LocalRepository>> {
-    let result: Vec<LocalRepository> = vec![];
-    let mut result: Arc<Mutex<Vec<LocalRepository>>> = Arc::new(Mutex::new(result));
-
+fn walk_repositories(config: &Config<'_>, index: &mut Index) -> Result<Vec<LocalRepository>> {
+    let mut result: Vec<LocalRepository> = vec![];
     for root in config.roots() {
-        walk_repository(root, &mut result)?;
+        result.extend(scan_root(config, index, root)?);
     }
-    let result = result.lock().unwrap().to_vec();
     Ok(result)
 }
 
-fn list_repos(config: &Config<'_>, profile: &str) -> Result<Vec<LocalRepository>> {
+fn list_repos(
+    config: &Config<'_>,
+    index: &mut Index,
+    profile: &str,
+) -> Result<Vec<LocalRepository>> {
     let repo_config = config.profile(profile)?;
-    let result: Vec<LocalRepository> = vec![];
-    let mut result: Arc<Mutex<Vec<LocalRepository>>> = Arc::new(Mutex::new(result));
-    walk_repository(&repo_config.root, &mut result)?;
-    let result = result.lock().unwrap().to_vec();
-    Ok(result)
+    scan_root(config, index, &repo_config.root)
+}
+
+/// Returns the root that contains `path`.
+pub fn find_root<'a>(config: &'a Config<'_>, path: &Path) -> Option<&'a String> {
+    config
+        .roots()
+        .into_iter()
+        .filter_map(|root| real_path(root).map(|real| (root, real)))
+        .filter(|(_, real)| path.starts_with(real))
+        .max_by_key(|(_, real)| real.as_os_str().len())
+        .map(|(root, _)| root)
+}
+
+/// Returns the repositories of the selected profile, or of every root.
+pub fn repositories(config: &Config<'_>) -> Result<Vec<LocalRepository>> {
+    let mut index = Index::load();
+    let repos = if let Some(profile) = config.profile {
+        list_repos(config, &mut index, profile)?
+    } else {
+        walk_repositories(config, &mut index)?
+    };
+    // the index is only a cache, listing goes on without it
+    if let Err(e) = index.save() {
+        eprintln!("warning: failed to save index: {:#}", e);
+    }
+    Ok(repos)
+}
+
+/// Returns the repositories matching the query and the filter, the best match first.
+pub fn select(config: &Config<'_>) -> Result<Vec<LocalRepository>> {
+    let repos = repositories(config)?;
+    let tags = Tags::load(config)?;
+    let fuzzy = FuzzyVec::from_vec(repos);
+    Ok(fuzzy
+        .search(&config.query)
+        .into_iter()
+        .filter(|repo| config.filter.matches(config, repo, &tags))
+        .cloned()
+        .collect())
 }
 
 fn each_repo(
     config: &Config<'_>,
     f: fn(&Config<'_>, &Vec<&LocalRepository>) -> Result<()>,
 ) -> Result<()> {
-    let repos = if let Some(profile) = config.profile {
-        list_repos(config, profile)?
+    let repos = select(config)?;
+    f(config, &repos.iter().collect())
+}
+
+pub fn reindex(config: &Config<'_>) -> Result<()> {
+    let mut index = Index::load();
+    let roots = if let Some(profile) = config.profile {
+        let mut roots = BTreeSet::new();
+        roots.insert(&config.profile(profile)?.root);
+        roots
     } else {
-        walk_repositories(config)?
+        config.roots()
     };
-    let fuzzy = FuzzyVec::from_vec(repos);
-    let repos = fuzzy.search(&config.query);
-    f(config, &repos)
+    for root in roots {
+        let repos = reindex_root(&mut index, root, &config.walk_options(root), true)?;
+        println!("indexed {} repositories in {}", repos.len(), root);
+    }
+    index.save()
+}
+
+/// A repository as printed by `list --json`.
+#[derive(Debug, Serialize)]
+struct RepositoryInfo<'a> {
+    path: String,
+    relpath: String,
+    host: Option<String>,
+    owner: Option<String>,
+    name: Option<String>,
+    backend: &'static str,
+    profile: Option<&'a str>,
+    url: Option<&'a str>,
+    tags: Vec<String>,
+}
+
+impl<'a> RepositoryInfo<'a> {
+    fn new(config: &'a Config<'_>, repo: &'a LocalRepository, tags: &Tags) -> RepositoryInfo<'a> {
+        let profile = repo.profile(config);
+        let (host, path) = repo.host_and_path_in(repo.layout(config));
+        let (owner, name) = layout::split_path(&path);
+        RepositoryInfo {
+            path: repo.path.to_string_lossy().into_owned(),
+            relpath: repo.relpath.to_string_lossy().into_owned(),
+            owner: Some(owner.to_owned()).filter(|s| !s.is_empty()),
+            name: Some(name.to_owned()).filter(|s| !s.is_empty()),
+            host,
+            backend: repo.backend.name(),
+            profile,
+            url: repo.url.as_deref(),
+            tags: tags.of(config, repo).into_iter().collect(),
+        }
+    }
 }
 
 pub fn list(config: &Config<'_>) -> Result<()> {
-    each_repo(config, |_, repos| {
-        for repo in repos {
-            println!("{}", repo.path);
+    each_repo(config, |config, repos| {
+        match config.format {
+            ListFormat::Path => {
+                for repo in repos {
+                    println!("{}", repo.path.display());
+                }
+            }
+            ListFormat::Relpath => {
+                for repo in repos {
+                    println!("{}", repo.relpath.display());
+                }
+            }
+            ListFormat::Url => {
+                for repo in repos {
+                    match &repo.url {
+                        Some(url) => println!("{}", url),
+                        None => debug!("remote url not found {:?}", repo.path),
+                    }
+                }
+            }
+            ListFormat::Json => {
+                let tags = Tags::load(config)?;
+                let infos: Vec<RepositoryInfo> = repos
+                    .iter()
+                    .map(|repo| RepositoryInfo::new(config, repo, &tags))
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&infos)?);
+            }
+            ListFormat::Ndjson => {
+                let tags = Tags::load(config)?;
+                for repo in repos {
+                    let info = RepositoryInfo::new(config, repo, &tags);
+                    println!("{}", serde_json::to_string(&info)?);
+                }
+            }
+            ListFormat::Tags => {
+                let tags = Tags::load(config)?;
+                for repo in repos {
+                    let repo_tags: Vec<String> = tags.of(config, repo).into_iter().collect();
+                    println!("{}\t{}", repo.relpath.display(), repo_tags.join(","));
+                }
+            }
         }
         Ok(())
     })
@@ -250,8 +311,9 @@ pub fn update(config: &Config<'_>) -> Result<()> {
                 url: None,
                 path: repo.path.clone(),
                 host: None,
+                branch: None,
             };
-            println!("update {}", &opt.path);
+            println!("update {}", opt.path.display());
             repo.backend.update(&opt)?;
             println!();
         }
@@ -271,17 +333,37 @@ pub fn look(config: &Config<'_>) -> Result<()> {
     })
 }
 
+/// Removes the clone and its index entry.
+pub fn remove_repository(
+    config: &Config<'_>,
+    index: &mut Index,
+    repo: &LocalRepository,
+) -> Result<()> {
+    let root = find_root(config, &repo.path);
+    let fresh = root.map(|root| index.is_fresh(root)).unwrap_or(false);
+    fs::remove_dir_all(&repo.path)?;
+    if let Some(root) = root {
+        if fresh {
+            index.remove(root, &repo.path);
+        } else {
+            index.invalidate(root);
+        }
+    }
+    println!("removed {}", repo.path.display());
+    Ok(())
+}
+
 pub fn remove(config: &Config<'_>) -> Result<()> {
-    each_repo(config, |_, repos| {
+    each_repo(config, |config, repos| {
+        let mut index = Index::load();
         for repo in repos {
-            println!("{}", &repo.path);
+            println!("{}", repo.path.display());
             if confirm("do you want to remove this? [Y/n]", "Y", Some("Y"))? {
-                fs::remove_dir_all(&repo.path)?;
-                println!("removed {}", &repo.path);
+                remove_repository(config, &mut index, repo)?;
             }
             println!();
         }
-        Ok(())
+        index.save()
     })
 }
 
@@ -290,10 +372,10 @@ pub fn each_exec(config: &Config<'_>) -> Result<()> {
         for repo in repos {
             if let Some(cmd) = config.each_cmd {
                 if config.dry_run {
-                    println!("{} : dry-run {:?} ", &repo.path, &cmd);
+                    println!("{} : dry-run {:?} ", repo.path.display(), &cmd);
                 } else {
-                    println!("{} : exec {:?} ", &repo.path, &cmd);
-                    run_with_work_dir(&cmd, &repo.path)?;
+                    println!("{} : exec {:?} ", repo.path.display(), &cmd);
+                    run_with_work_dir(cmd, &repo.path)?;
                 }
                 println!();
             }
@@ -312,34 +394,11 @@ struct FuzzyVec {
 }
 
 impl FuzzyVec {
-    /// Creates a `FuzzyVec`.
-    pub fn new() -> FuzzyVec {
-        FuzzyVec {
-            entries: Vec::new(),
-        }
-    }
-
     /// Creates a `FuzzyVec` from `entries`.
     pub fn from_vec(entries: Vec<LocalRepository>) -> FuzzyVec {
         FuzzyVec { entries }
     }
 
-    /// Returns the number of entiries.
-    #[inline]
-    pub fn len(&self) -> usize {
-        self.entries.len()
-    }
-
-    // Clears the contents.
-    pub fn clear(&mut self) {
-        self.entries.clear();
-    }
-
-    /// appends a entry.
-    pub fn append(&mut self, entry: LocalRepository) {
-        self.entries.push(entry);
-    }
-
     /// Searches entiries for `query` in a fuzzy way and returns the result
     /// ordered by the similarity.
     pub fn search(&self, query: &str) -> Vec<&LocalRepository> {
@@ -371,15 +430,15 @@ fn fuzzy_search<'a>(entries: &'a [LocalRepository], query: &str) -> Vec<&'a Loca
     // Filter entries by the query.
     let mut filtered = entries
         .iter()
-        .filter(|repo| is_fuzzily_matched(&repo.relpath, query))
+        .filter(|repo| is_fuzzily_matched(&repo.relpath.to_string_lossy(), query))
         .collect::<Vec<_>>();
-    filtered.sort_by_cached_key(|entry| compute_score(&entry.relpath, query));
+    filtered.sort_by_cached_key(|entry| compute_score(&entry.relpath.to_string_lossy(), query));
     filtered
 }
 
 /// Computes the similarity. Lower is more similar.
 fn compute_score(entry: &str, query: &str) -> u8 {
-    let mut score = std::u8::MAX;
+    let mut score = u8::MAX;
 
     if entry == query {
         score -= 100;
@@ -395,16 +454,81 @@ fn compute_score(entry: &str, query: &str) -> u8 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::canonicalize;
+    use crate::vcs::VCSBackend;
+
+    #[test]
+    fn repository_info() {
+        let config = Config {
+            meta_path: PathBuf::from("/nonexistent/rrc-meta.toml"),
+            ..Default::default()
+        };
+        let tags = Tags::load(&config).unwrap();
+        let root = config.profile("default").unwrap().root.clone();
+        let repo = LocalRepository {
+            path: Path::new(&root).join("github.com/mopemope/rrc"),
+            relpath: PathBuf::from("github.com/mopemope/rrc"),
+            backend: VCSBackend::GitBackend,
+            url: Some("git@gitlab.com:group/sub/rrc.git".to_owned()),
+        };
+        let info = RepositoryInfo::new(&config, &repo, &tags);
+        assert_eq!(info.host.as_deref(), Some("gitlab.com"));
+        assert_eq!(info.owner.as_deref(), Some("group/sub"));
+        assert_eq!(info.name.as_deref(), Some("rrc"));
+        assert_eq!(info.backend, "git");
+
+        let repo = LocalRepository { url: None, ..repo };
+        let info = RepositoryInfo::new(&config, &repo, &tags);
+        assert_eq!(info.host.as_deref(), Some("github.com"));
+        assert_eq!(info.owner.as_deref(), Some("mopemope"));
+        assert_eq!(info.name.as_deref(), Some("rrc"));
+    }
+
+    #[test]
+    fn reindex_reads_remote() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = fs::canonicalize(dir.path()).unwrap();
+        let repo = root.join("github.com/foo/bar");
+        crate::utils::init_repo(&repo);
+        crate::utils::git(
+            &repo,
+            &["remote", "add", "origin", "https://github.com/foo/bar"],
+        );
+        let (root, opts) = (root.to_str().unwrap(), WalkOptions::default());
+        let mut index = Index::default();
+        let url = |repos: Vec<LocalRepository>| repos[0].url.clone().unwrap();
+        let repos = reindex_root(&mut index, root, &opts, false).unwrap();
+        assert_eq!(url(repos), "https://github.com/foo/bar");
+
+        crate::utils::git(
+            &repo,
+            &["remote", "set-url", "origin", "https://github.com/foo/baz"],
+        );
+        let repos = reindex_root(&mut index, root, &opts, false).unwrap();
+        assert_eq!(url(repos), "https://github.com/foo/bar");
+        let repos = reindex_root(&mut index, root, &opts, true).unwrap();
+        assert_eq!(url(repos), "https://github.com/foo/baz");
+    }
 
     #[test]
-    fn read_dir() {
-        env_logger::try_init();
-        let root_path = "/home/ma2/repos";
-        let root_path = canonicalize(root_path).unwrap();
-        let result: Vec<LocalRepository> = vec![];
-        let mut result: Arc<Mutex<Vec<LocalRepository>>> = Arc::new(Mutex::new(result));
-        walk_repository(root_path.to_str().unwrap(), &mut result).unwrap();
-        println!("repos: {:?}", result);
+    fn repository_info_in_layout() {
+        let mut config = Config {
+            meta_path: PathBuf::from("/nonexistent/rrc-meta.toml"),
+            ..Default::default()
+        };
+        let profile = config.repos.get_mut("default").unwrap();
+        profile.root = "/repos".to_owned();
+        profile.layout = "{owner}/{repo}".to_owned();
+        let tags = Tags::load(&config).unwrap();
+        let repo = LocalRepository {
+            path: PathBuf::from("/repos/group/sub/project"),
+            relpath: PathBuf::from("group/sub/project"),
+            backend: VCSBackend::GitBackend,
+            url: None,
+        };
+        let info = RepositoryInfo::new(&config, &repo, &tags);
+        assert_eq!(info.profile, Some("default"));
+        assert_eq!(info.host, None);
+        assert_eq!(info.owner.as_deref(), Some("group/sub"));
+        assert_eq!(info.name.as_deref(), Some("project"));
     }
 }
@@ -1,19 +1,109 @@
+mod adopt;
 mod config;
+mod doctor;
+mod filter;
+mod forge;
+mod ghq;
+mod import;
+mod index;
+mod layout;
 mod local;
+mod manifest;
+mod relocate;
 mod remote;
+mod tag;
 mod utils;
 mod vcs;
+mod walk;
 
 use anyhow::{Context, Result};
-use clap::{App, AppSettings, Arg, Command, SubCommand};
+use clap::{App, AppSettings, Arg, ArgGroup, ArgMatches, Command, SubCommand};
+use config::{ListFormat, Protocol};
+use filter::{parse_duration, Filter};
 use lazy_static::lazy_static;
 use log::{debug, error};
 use std::env;
+use std::path::Path;
 
 lazy_static! {
     pub static ref CONFIG_PATH: String = config::get_config_path();
 }
 
+fn filter_args(app: App<'static>) -> App<'static> {
+    app.arg(
+        Arg::with_name("host")
+            .multiple_occurrences(true)
+            .value_name("host")
+            .long("host")
+            .help("Filter by host"),
+    )
+    .arg(
+        Arg::with_name("owner")
+            .multiple_occurrences(true)
+            .value_name("owner")
+            .long("owner")
+            .help("Filter by owner"),
+    )
+    .arg(
+        Arg::with_name("vcs")
+            .multiple(false)
+            .value_name("vcs")
+            .long("vcs")
+            .possible_values(["git", "hg"])
+            .help("Filter by vcs backend"),
+    )
+    .arg(
+        Arg::with_name("dirty")
+            .multiple(false)
+            .long("dirty")
+            .help("Only repositories with uncommitted changes"),
+    )
+    .arg(
+        Arg::with_name("tag")
+            .multiple_occurrences(true)
+            .value_name("tag")
+            .long("tag")
+            .help("Filter by tag or group"),
+    )
+    .arg(
+        Arg::with_name("stale")
+            .multiple(false)
+            .value_name("duration")
+            .long("stale")
+            .help("Only repositories without commits for the duration (e.g. 90d)"),
+    )
+}
+
+fn parse_filter<'a>(m: &'a ArgMatches) -> Result<Filter<'a>> {
+    let mut filter: Filter = Default::default();
+    if let Some(hosts) = m.values_of("host") {
+        filter.hosts = hosts.collect();
+    }
+    if let Some(owners) = m.values_of("owner") {
+        filter.owners = owners.collect();
+    }
+    if let Some(vcs) = m.value_of("vcs") {
+        filter.vcs = Some(vcs.parse()?);
+    }
+    if let Some(tags) = m.values_of("tag") {
+        filter.tags = tags.collect();
+    }
+    filter.dirty = m.is_present("dirty");
+    if let Some(stale) = m.value_of("stale") {
+        filter.stale = Some(parse_duration(stale)?);
+    }
+    Ok(filter)
+}
+
+fn parse_jobs(m: &ArgMatches) -> Result<usize> {
+    match m.value_of("jobs") {
+        Some(jobs) => jobs
+            .parse()
+            .with_context(|| format!("invalid jobs {}", jobs)),
+        None => Ok(import::DEFAULT_JOBS),
+    }
+}
+
 fn make_app() -> App<'static> {
     Command::new("rrc")
         .version(env!("CARGO_PKG_VERSION"))
@@ -53,6 +143,26 @@ fn make_app() -> App<'static> {
                         .long("look")
                         .help("Look after get"),
                 )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .multiple(false)
+                        .short('d')
+                        .long("dry-run")
+                        .help("Show the clone url and the directory without cloning"),
+                )
+                .arg(
+                    Arg::with_name("ssh")
+                        .multiple(false)
+                        .long("ssh")
+                        .conflicts_with("https")
+                        .help("Clone over ssh"),
+                )
+                .arg(
+                    Arg::with_name("https")
+                        .multiple(false)
+                        .long("https")
+                        .help("Clone over https"),
+                )
                 .arg(
                     Arg::with_name("url")
                         .required(true)
@@ -61,7 +171,7 @@ fn make_app() -> App<'static> {
                         .help("Source repository url"),
                 ),
         )
-        .subcommand(
+        .subcommand(filter_args(
             SubCommand::with_name("list")
                 .about("List local repositories")
                 .arg(
@@ -72,6 +182,36 @@ fn make_app() -> App<'static> {
                         .long("profile")
                         .help("Select profile"),
                 )
+                .arg(
+                    Arg::with_name("nested")
+                        .multiple(false)
+                        .short('n')
+                        .long("nested")
+                        .help("Include repositories nested in other repositories"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .multiple(false)
+                        .value_name("format")
+                        .short('f')
+                        .long("format")
+                        .possible_values(["path", "relpath", "url", "tags", "json", "ndjson"])
+                        .help("Output format"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .multiple(false)
+                        .long("json")
+                        .conflicts_with_all(&["format", "ndjson"])
+                        .help("Output a JSON array"),
+                )
+                .arg(
+                    Arg::with_name("ndjson")
+                        .multiple(false)
+                        .long("ndjson")
+                        .conflicts_with("format")
+                        .help("Output one JSON object per line"),
+                )
                 .arg(
                     Arg::with_name("exact")
                         .multiple(false)
@@ -80,8 +220,8 @@ fn make_app() -> App<'static> {
                         .long("exact")
                         .help("Perform an exact match"),
                 ),
-        )
-        .subcommand(
+        ))
+        .subcommand(filter_args(
             SubCommand::with_name("update")
                 .about("Update local repositories")
                 .arg(
@@ -92,6 +232,13 @@ fn make_app() -> App<'static> {
                         .long("profile")
                         .help("Select profile"),
                 )
+                .arg(
+                    Arg::with_name("nested")
+                        .multiple(false)
+                        .short('n')
+                        .long("nested")
+                        .help("Include repositories nested in other repositories"),
+                )
                 .arg(
                     Arg::with_name("exact")
                         .multiple(false)
@@ -100,7 +247,7 @@ fn make_app() -> App<'static> {
                         .long("exact")
                         .help("Perform an exact match"),
                 ),
-        )
+        ))
         .subcommand(
             SubCommand::with_name("look")
                 .about("Look local repository")
@@ -120,7 +267,7 @@ fn make_app() -> App<'static> {
                         .help("Perform an exact match"),
                 ),
         )
-        .subcommand(
+        .subcommand(filter_args(
             SubCommand::with_name("remove")
                 .about("Remove local repositories")
                 .arg(
@@ -138,8 +285,356 @@ fn make_app() -> App<'static> {
                         .value_name("query")
                         .help("Perform an exact match"),
                 ),
+        ))
+        .subcommand(
+            SubCommand::with_name("tag")
+                .about("Manage repository tags")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("add")
+                        .about("Add a tag to repositories")
+                        .arg(
+                            Arg::with_name("tag")
+                                .required(true)
+                                .value_name("tag")
+                                .help("Tag name"),
+                        )
+                        .arg(
+                            Arg::with_name("repository")
+                                .required(true)
+                                .multiple(true)
+                                .value_name("repository")
+                                .help("Repository url, relpath or path"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("remove")
+                        .about("Remove a tag from repositories")
+                        .arg(
+                            Arg::with_name("tag")
+                                .required(true)
+                                .value_name("tag")
+                                .help("Tag name"),
+                        )
+                        .arg(
+                            Arg::with_name("repository")
+                                .required(true)
+                                .multiple(true)
+                                .value_name("repository")
+                                .help("Repository url, relpath or path"),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List tagged repositories")
+                        .arg(
+                            Arg::with_name("repository")
+                                .value_name("repository")
+                                .help("Repository url, relpath or path"),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("import")
+                .about("Clone repositories listed in a file or stdin")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                )
+                .arg(
+                    Arg::with_name("update")
+                        .multiple(false)
+                        .short('u')
+                        .long("update")
+                        .help("Update local repositories if cloned already"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .multiple(false)
+                        .value_name("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .help("Number of concurrent clones (default 4)"),
+                )
+                .arg(
+                    Arg::with_name("github-org")
+                        .multiple(false)
+                        .value_name("[host/]owner")
+                        .long("github-org")
+                        .help("Clone the repositories of a GitHub organization or user"),
+                )
+                .arg(
+                    Arg::with_name("gitlab-group")
+                        .multiple(false)
+                        .value_name("[host/]group")
+                        .long("gitlab-group")
+                        .help("Clone the projects of a GitLab group and its subgroups"),
+                )
+                .arg(
+                    Arg::with_name("forge")
+                        .multiple(false)
+                        .value_name("name")
+                        .long("forge")
+                        .requires("owner")
+                        .help("Clone the repositories of --owner on a forge (github, gitlab, gitea, forgejo, bitbucket or a host)"),
+                )
+                .arg(
+                    Arg::with_name("owner")
+                        .multiple(false)
+                        .value_name("owner")
+                        .long("owner")
+                        .requires("forge")
+                        .help("User, organization, group or workspace of the forge"),
+                )
+                .group(
+                    ArgGroup::new("source")
+                        .args(&["github-org", "gitlab-group", "forge"])
+                        .conflicts_with("file"),
+                )
+                .arg(
+                    Arg::with_name("no-archived")
+                        .multiple(false)
+                        .long("no-archived")
+                        .requires("source")
+                        .help("Skip archived repositories"),
+                )
+                .arg(
+                    Arg::with_name("no-forks")
+                        .multiple(false)
+                        .long("no-forks")
+                        .requires("source")
+                        .help("Skip forks"),
+                )
+                .arg(
+                    Arg::with_name("file")
+                        .multiple(false)
+                        .value_name("FILE")
+                        .help("File of repository urls, one per line (default stdin)"),
+                ),
         )
+        .subcommand(filter_args(
+            SubCommand::with_name("export")
+                .about("Print a manifest of local repositories")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .multiple(false)
+                        .value_name("format")
+                        .short('f')
+                        .long("format")
+                        .possible_values(["toml", "json"])
+                        .help("Manifest format (default toml)"),
+                ),
+        ))
+        .subcommand(filter_args(
+            SubCommand::with_name("snapshot")
+                .about("Print the current revisions of local repositories")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .multiple(false)
+                        .value_name("format")
+                        .short('f')
+                        .long("format")
+                        .possible_values(["toml", "json"])
+                        .help("Lockfile format (default toml)"),
+                ),
+        ))
         .subcommand(
+            SubCommand::with_name("restore")
+                .about("Check out the revisions of a snapshot")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .multiple(false)
+                        .short('d')
+                        .long("dry-run")
+                        .help("Dry run"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .multiple(false)
+                        .value_name("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .help("Number of concurrent clones (default 4)"),
+                )
+                .arg(
+                    Arg::with_name("lockfile")
+                        .required(true)
+                        .multiple(false)
+                        .value_name("FILE")
+                        .help("Snapshot file (.toml or .json)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("sync")
+                .about("Clone repositories of a manifest and report the others")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                )
+                .arg(
+                    Arg::with_name("update")
+                        .multiple(false)
+                        .short('u')
+                        .long("update")
+                        .help("Update local repositories if cloned already"),
+                )
+                .arg(
+                    Arg::with_name("prune").multiple(false).long("prune").help(
+                        "Remove repositories not in the manifest unless they have local changes",
+                    ),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .multiple(false)
+                        .short('d')
+                        .long("dry-run")
+                        .help("Dry run"),
+                )
+                .arg(
+                    Arg::with_name("jobs")
+                        .multiple(false)
+                        .value_name("jobs")
+                        .short('j')
+                        .long("jobs")
+                        .help("Number of concurrent clones (default 4)"),
+                )
+                .arg(
+                    Arg::with_name("manifest")
+                        .required(true)
+                        .multiple(false)
+                        .value_name("FILE")
+                        .help("Manifest file (.toml or .json)"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("adopt")
+                .about("Move existing clones into the managed layout")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                )
+                .arg(
+                    Arg::with_name("dry-run")
+                        .multiple(false)
+                        .short('d')
+                        .long("dry-run")
+                        .help("Dry run"),
+                )
+                .arg(
+                    Arg::with_name("copy")
+                        .multiple(false)
+                        .long("copy")
+                        .help("Copy instead of move"),
+                )
+                .arg(
+                    Arg::with_name("dir")
+                        .required(true)
+                        .multiple(true)
+                        .value_name("dir")
+                        .help("Existing clone"),
+                ),
+        )
+        .subcommand(filter_args(
+            SubCommand::with_name("relocate")
+                .about("Move repositories whose remote url no longer matches their directory")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                )
+                .arg(
+                    Arg::with_name("apply")
+                        .multiple(false)
+                        .long("apply")
+                        .help("Move the repositories instead of listing them"),
+                )
+                .arg(
+                    Arg::with_name("query")
+                        .multiple(false)
+                        .value_name("query")
+                        .help("Only repositories matching the query"),
+                ),
+        ))
+        .subcommand(
+            SubCommand::with_name("migrate-from-ghq")
+                .about("Write the ghq settings of git config to the config file")
+                .arg(
+                    Arg::with_name("dry-run")
+                        .multiple(false)
+                        .short('d')
+                        .long("dry-run")
+                        .help("Print the config instead of writing it"),
+                )
+                .arg(
+                    Arg::with_name("force")
+                        .multiple(false)
+                        .short('f')
+                        .long("force")
+                        .help("Merge into the existing config file, dropping its comments"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("doctor")
+                .about("Check the config and the roots for problems")
+                .arg(
+                    Arg::with_name("fix")
+                        .multiple(false)
+                        .long("fix")
+                        .help("Remove the empty directories of failed clones"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("reindex")
+                .about("Rebuild the repository index")
+                .arg(
+                    Arg::with_name("profile")
+                        .multiple(false)
+                        .value_name("profile")
+                        .short('p')
+                        .long("profile")
+                        .help("Select profile"),
+                ),
+        )
+        .subcommand(filter_args(
             SubCommand::with_name("each")
                 .about("Execute command for each local repositories")
                 .arg(
@@ -157,6 +652,13 @@ fn make_app() -> App<'static> {
                         .long("dry-run")
                         .help("Dry run"),
                 )
+                .arg(
+                    Arg::with_name("nested")
+                        .multiple(false)
+                        .short('n')
+                        .long("nested")
+                        .help("Include repositories nested in other repositories"),
+                )
                 .arg(
                     Arg::with_name("exact")
                         .multiple(false)
@@ -173,7 +675,7 @@ fn make_app() -> App<'static> {
                         .required(true)
                         .help("Run command"),
                 ),
-        )
+        ))
 }
 
 fn run() -> Result<()> {
@@ -190,6 +692,12 @@ fn run() -> Result<()> {
             let update = m.is_present("update");
             config.look = m.is_present("look");
             config.profile = m.value_of("profile");
+            config.dry_run = m.is_present("dry-run");
+            if m.is_present("ssh") {
+                config.protocol = Some(Protocol::Ssh);
+            } else if m.is_present("https") {
+                config.protocol = Some(Protocol::Https);
+            }
             for url in urls {
                 debug!("repository url {}", url);
                 if update {
@@ -202,6 +710,15 @@ fn run() -> Result<()> {
         }
         Some(("list", m)) => {
             config.profile = m.value_of("profile");
+            config.filter = parse_filter(m)?;
+            config.nested = m.is_present("nested");
+            config.format = if m.is_present("json") {
+                ListFormat::Json
+            } else if m.is_present("ndjson") {
+                ListFormat::Ndjson
+            } else {
+                m.value_of("format").unwrap_or("path").parse()?
+            };
             if let Some(query) = m.value_of("exact") {
                 config.query = query.to_owned();
             }
@@ -209,6 +726,8 @@ fn run() -> Result<()> {
         }
         Some(("update", m)) => {
             config.profile = m.value_of("profile");
+            config.filter = parse_filter(m)?;
+            config.nested = m.is_present("nested");
             if let Some(query) = m.value_of("exact") {
                 config.query = query.to_owned();
             }
@@ -223,13 +742,110 @@ fn run() -> Result<()> {
         }
         Some(("remove", m)) => {
             config.profile = m.value_of("profile");
+            config.filter = parse_filter(m)?;
             if let Some(query) = m.value_of("exact") {
                 config.query = query.to_owned();
             }
             local::remove(&config)
         }
+        Some(("tag", m)) => match m.subcommand() {
+            Some(("add", m)) => {
+                let tag = m.value_of("tag").context("require tag")?;
+                let repos: Vec<&str> = m.values_of("repository").unwrap().collect();
+                tag::add(&config, tag, &repos)
+            }
+            Some(("remove", m)) => {
+                let tag = m.value_of("tag").context("require tag")?;
+                let repos: Vec<&str> = m.values_of("repository").unwrap().collect();
+                tag::remove(&config, tag, &repos)
+            }
+            Some(("list", m)) => tag::list(&config, m.value_of("repository")),
+            _ => unreachable!(),
+        },
+        Some(("import", m)) => {
+            config.profile = m.value_of("profile");
+            let jobs = parse_jobs(m)?;
+            let update = m.is_present("update");
+            let filter = forge::RepoFilter {
+                archived: !m.is_present("no-archived"),
+                forks: !m.is_present("no-forks"),
+            };
+            let source = if let Some(name) = m.value_of("forge") {
+                let (forge, host) = forge::resolve(&config, name)?;
+                let owner = m.value_of("owner").context("require owner")?;
+                Some((forge, host, owner))
+            } else if let Some(owner) = m.value_of("github-org") {
+                let (host, owner) = forge::split_owner(&config, forge::Forge::Github, owner);
+                Some((forge::Forge::Github, host.to_owned(), owner))
+            } else if let Some(group) = m.value_of("gitlab-group") {
+                let (host, group) = forge::split_owner(&config, forge::Forge::Gitlab, group);
+                Some((forge::Forge::Gitlab, host.to_owned(), group))
+            } else {
+                None
+            };
+            if let Some((forge, host, owner)) = source {
+                return forge::import(&config, forge, &host, owner, filter, update, jobs);
+            }
+            import::import(&config, m.value_of("file"), update, jobs)
+        }
+        Some(("export", m)) => {
+            config.profile = m.value_of("profile");
+            config.filter = parse_filter(m)?;
+            let format = m.value_of("format").unwrap_or("toml").parse()?;
+            manifest::export(&config, format, false)
+        }
+        Some(("snapshot", m)) => {
+            config.profile = m.value_of("profile");
+            config.filter = parse_filter(m)?;
+            let format = m.value_of("format").unwrap_or("toml").parse()?;
+            manifest::export(&config, format, true)
+        }
+        Some(("restore", m)) => {
+            config.profile = m.value_of("profile");
+            config.dry_run = m.is_present("dry-run");
+            let jobs = parse_jobs(m)?;
+            let path = m.value_of("lockfile").context("require lockfile")?;
+            manifest::restore(&config, Path::new(path), jobs)
+        }
+        Some(("sync", m)) => {
+            config.profile = m.value_of("profile");
+            config.dry_run = m.is_present("dry-run");
+            let jobs = parse_jobs(m)?;
+            let path = m.value_of("manifest").context("require manifest")?;
+            manifest::sync(
+                &config,
+                Path::new(path),
+                m.is_present("update"),
+                m.is_present("prune"),
+                jobs,
+            )
+        }
+        Some(("adopt", m)) => {
+            config.profile = m.value_of("profile");
+            config.dry_run = m.is_present("dry-run");
+            let dirs: Vec<&str> = m.values_of("dir").unwrap().collect();
+            adopt::adopt(&config, &dirs, m.is_present("copy"))
+        }
+        Some(("relocate", m)) => {
+            config.profile = m.value_of("profile");
+            config.filter = parse_filter(m)?;
+            if let Some(query) = m.value_of("query") {
+                config.query = query.to_owned();
+            }
+            relocate::relocate(&config, m.is_present("apply"))
+        }
+        Some(("migrate-from-ghq", m)) => {
+            ghq::migrate(config_path, m.is_present("dry-run"), m.is_present("force"))
+        }
+        Some(("doctor", m)) => doctor::doctor(&config, config_path, m.is_present("fix")),
+        Some(("reindex", m)) => {
+            config.profile = m.value_of("profile");
+            local::reindex(&config)
+        }
         Some(("each", m)) => {
             config.profile = m.value_of("profile");
+            config.filter = parse_filter(m)?;
+            config.nested = m.is_present("nested");
             if let Some(query) = m.value_of("exact") {
                 config.query = query.to_owned();
             }
@@ -0,0 +1,480 @@
+use crate::config::Config;
+use crate::import::{import_entries, ImportEntry};
+use crate::index::Index;
+use crate::local::{remove_repository, repositories, LocalRepository};
+use crate::remote::parse_url;
+use crate::tag::Tags;
+use crate::vcs::VCSBackend;
+use anyhow::{Context, Result};
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+///
+/// A reproducible list of clones.
+///
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default, rename = "repository")]
+    pub repositories: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcs: Option<String>,
+    /// commit of a snapshot
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revision: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ManifestFormat {
+    Toml,
+    Json,
+}
+
+impl FromStr for ManifestFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<ManifestFormat> {
+        match s {
+            "toml" => Ok(ManifestFormat::Toml),
+            "json" => Ok(ManifestFormat::Json),
+            _ => Err(anyhow::format_err!("unknown manifest format {}", s)),
+        }
+    }
+}
+
+impl ManifestFormat {
+    /// JSON for `.json` files, TOML otherwise.
+    fn of(path: &Path) -> ManifestFormat {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => ManifestFormat::Json,
+            _ => ManifestFormat::Toml,
+        }
+    }
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Manifest> {
+        let s =
+            fs::read_to_string(path).with_context(|| format!("failed read {}", path.display()))?;
+        Manifest::parse(&s, ManifestFormat::of(path))
+            .with_context(|| format!("failed parse manifest. path: {}", path.display()))
+    }
+
+    pub fn parse(s: &str, format: ManifestFormat) -> Result<Manifest> {
+        Ok(match format {
+            ManifestFormat::Toml => toml::from_str(s)?,
+            ManifestFormat::Json => serde_json::from_str(s)?,
+        })
+    }
+
+    pub fn to_string(&self, format: ManifestFormat) -> Result<String> {
+        Ok(match format {
+            ManifestFormat::Toml => toml::to_string(self)?,
+            ManifestFormat::Json => serde_json::to_string_pretty(self)? + "\n",
+        })
+    }
+
+    fn entries(&self) -> Result<Vec<ImportEntry>> {
+        self.repositories
+            .iter()
+            .map(|repo| {
+                Ok(ImportEntry {
+                    url: repo.url.to_owned(),
+                    branch: repo.branch.clone(),
+                    vcs: repo.vcs.as_deref().map(str::parse).transpose()?,
+                    profile: repo.profile.clone(),
+                })
+            })
+            .collect()
+    }
+}
+
+fn manifest_entry(
+    config: &Config<'_>,
+    repo: &LocalRepository,
+    snapshot: bool,
+) -> Option<ManifestEntry> {
+    let url = match &repo.url {
+        Some(url) => url.to_owned(),
+        None => {
+            eprintln!("skip {} : no remote url", repo.path.display());
+            return None;
+        }
+    };
+    let profile = repo.profile(config).map(|name| name.to_owned());
+    let branch = repo.backend.current_branch(&repo.path).unwrap_or_else(|e| {
+        debug!("{} path:{:?}", e, repo.path);
+        None
+    });
+    let revision = if snapshot {
+        match repo.backend.current_revision(&repo.path) {
+            Ok(Some(revision)) => Some(revision),
+            Ok(None) => {
+                eprintln!("warning {} : no revision to record", repo.path.display());
+                None
+            }
+            Err(e) => {
+                eprintln!(
+                    "warning {} : no revision to record, {}",
+                    repo.path.display(),
+                    e
+                );
+                None
+            }
+        }
+    } else {
+        None
+    };
+    Some(ManifestEntry {
+        url,
+        profile,
+        branch,
+        vcs: Some(repo.backend.name().to_owned()),
+        revision,
+    })
+}
+
+/// Prints the manifest of the repositories matching the filter.
+/// A snapshot also records the current revisions.
+pub fn export(config: &Config<'_>, format: ManifestFormat, snapshot: bool) -> Result<()> {
+    let repos = repositories(config)?;
+    let tags = Tags::load(config)?;
+    let manifest = Manifest {
+        repositories: repos
+            .iter()
+            .filter(|repo| config.filter.matches(config, repo, &tags))
+            .filter_map(|repo| manifest_entry(config, repo, snapshot))
+            .collect(),
+    };
+    print!("{}", manifest.to_string(format)?);
+    Ok(())
+}
+
+/// Returns why a repository must not be pruned, if any.
+fn prune_blocker(repo: &LocalRepository) -> Option<&'static str> {
+    match repo.backend.is_dirty(&repo.path) {
+        Ok(false) => {}
+        Ok(true) => return Some("uncommitted changes"),
+        Err(e) => {
+            debug!("{} path:{:?}", e, repo.path);
+            return Some("failed to check status");
+        }
+    }
+    match repo.backend.has_stash(&repo.path) {
+        Ok(false) => {}
+        Ok(true) => return Some("stashed changes"),
+        Err(e) => {
+            debug!("{} path:{:?}", e, repo.path);
+            return Some("failed to check stashes");
+        }
+    }
+    match repo.backend.has_unpushed(&repo.path) {
+        Ok(false) => None,
+        Ok(true) => Some("unpushed commits"),
+        Err(e) => {
+            debug!("{} path:{:?}", e, repo.path);
+            Some("failed to check unpushed commits")
+        }
+    }
+}
+
+///
+/// Clones what is missing from the manifest and reports the repositories not in it.
+/// With `prune` they are removed unless they have local work.
+///
+pub fn sync(
+    config: &Config<'_>,
+    path: &Path,
+    update: bool,
+    prune: bool,
+    jobs: usize,
+) -> Result<()> {
+    let manifest = Manifest::load(path)?;
+    let summary = import_entries(config, &manifest.entries()?, update, jobs)?;
+
+    let extra: Vec<LocalRepository> = repositories(config)?
+        .into_iter()
+        .filter(|repo| !summary.paths.contains(&repo.path))
+        .collect();
+    if prune && !summary.failed.is_empty() {
+        eprintln!("skip pruning: some entries of the manifest failed");
+    }
+    let prune = prune && summary.failed.is_empty();
+    let mut index = Index::load();
+    let mut removed = false;
+    for repo in &extra {
+        if !prune {
+            println!("not in manifest {}", repo.path.display());
+        } else if let Some(reason) = prune_blocker(repo) {
+            println!("keep {} : {}", repo.path.display(), reason);
+        } else if config.dry_run {
+            println!("{} : dry-run remove", repo.path.display());
+        } else {
+            remove_repository(config, &mut index, repo)?;
+            removed = true;
+        }
+    }
+    if removed {
+        index.save()?;
+    }
+    summary.report()
+}
+
+/// Revisions are commit ids, nothing the vcs could take as an option.
+fn check_revision(revision: &str) -> Result<()> {
+    if revision.len() >= 7 && revision.chars().all(|c| c.is_ascii_hexdigit()) {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!("invalid revision {}", revision))
+    }
+}
+
+enum Restored {
+    Unchanged,
+    CheckedOut,
+}
+
+fn restore_entry(config: &Config<'_>, entry: &ManifestEntry, revision: &str) -> Result<Restored> {
+    check_revision(revision)?;
+    let opt = parse_url(
+        config,
+        entry.profile.as_deref().or(config.profile),
+        &entry.url,
+    )?;
+    if config.dry_run && !opt.path.exists() {
+        println!("{} : dry-run checkout {}", opt.path.display(), revision);
+        return Ok(Restored::CheckedOut);
+    }
+    let vcs = match &entry.vcs {
+        Some(vcs) => vcs.parse()?,
+        None if opt.path.join(".hg").exists() => VCSBackend::MercurialBackend,
+        None => VCSBackend::GitBackend,
+    };
+    // a short revision is a prefix of the full one
+    let revision_prefix = revision.to_ascii_lowercase();
+    if let Some(current) = vcs.current_revision(&opt.path)? {
+        if current.to_ascii_lowercase().starts_with(&revision_prefix) {
+            return Ok(Restored::Unchanged);
+        }
+    }
+    if vcs.is_dirty(&opt.path)? {
+        return Err(anyhow::format_err!(
+            "{} has uncommitted changes",
+            opt.path.display()
+        ));
+    }
+    if config.dry_run {
+        println!("{} : dry-run checkout {}", opt.path.display(), revision);
+    } else {
+        vcs.checkout(&opt.path, revision)?;
+        println!("{} : checkout {}", opt.path.display(), revision);
+    }
+    Ok(Restored::CheckedOut)
+}
+
+/// Clones what is missing from the snapshot and checks out the recorded revisions.
+pub fn restore(config: &Config<'_>, path: &Path, jobs: usize) -> Result<()> {
+    let manifest = Manifest::load(path)?;
+    let summary = import_entries(config, &manifest.entries()?, false, jobs)?;
+    let failed_urls: BTreeSet<String> = summary.failed.iter().map(|(url, _)| url.clone()).collect();
+
+    let (mut unchanged, mut checked_out, mut skipped, mut failed) = (0, 0, 0, summary.failed.len());
+    for entry in &manifest.repositories {
+        if failed_urls.contains(&entry.url) {
+            continue;
+        }
+        let revision = match &entry.revision {
+            Some(revision) => revision,
+            None => {
+                println!("skip {} : no revision in the snapshot", entry.url);
+                skipped += 1;
+                continue;
+            }
+        };
+        match restore_entry(config, entry, revision) {
+            Ok(Restored::Unchanged) => unchanged += 1,
+            Ok(Restored::CheckedOut) => checked_out += 1,
+            Err(e) => {
+                eprintln!("failed {} : {}", entry.url, e);
+                failed += 1;
+            }
+        }
+    }
+    let imported = summary.report();
+    println!(
+        "checked out {}, unchanged {}, skipped {}, failed {}",
+        checked_out, unchanged, skipped, failed
+    );
+    imported?;
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!(
+            "failed to restore {} repositories",
+            failed
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{git, init_repo};
+
+    #[test]
+    fn manifest_round_trip() {
+        let manifest = Manifest {
+            repositories: vec![
+                ManifestEntry {
+                    url: "https://github.com/mopemope/rrc".to_owned(),
+                    profile: Some("default".to_owned()),
+                    branch: Some("master".to_owned()),
+                    vcs: Some("git".to_owned()),
+                    revision: Some("0123456789abcdef0123456789abcdef01234567".to_owned()),
+                },
+                ManifestEntry {
+                    url: "https://hg.example.com/foo/bar".to_owned(),
+                    profile: None,
+                    branch: None,
+                    vcs: Some("hg".to_owned()),
+                    revision: None,
+                },
+            ],
+        };
+        for format in [ManifestFormat::Toml, ManifestFormat::Json] {
+            let s = manifest.to_string(format).unwrap();
+            assert_eq!(Manifest::parse(&s, format).unwrap(), manifest);
+        }
+        let toml = manifest.to_string(ManifestFormat::Toml).unwrap();
+        assert!(toml.starts_with("[[repository]]\n"));
+
+        let entries = manifest.entries().unwrap();
+        assert_eq!(entries[1].vcs, Some(VCSBackend::MercurialBackend));
+        let manifest = Manifest::parse(
+            "[[repository]]\nurl = \"a/b\"\nvcs = \"svn\"\n",
+            ManifestFormat::Toml,
+        )
+        .unwrap();
+        assert!(manifest.entries().is_err());
+    }
+
+    #[test]
+    fn revision() {
+        assert!(check_revision("0123456789abcdef0123456789abcdef01234567").is_ok());
+        assert!(check_revision("abc1234").is_ok());
+        assert!(check_revision("abc").is_err());
+        assert!(check_revision("--upload-pack=x").is_err());
+        assert!(check_revision("main").is_err());
+    }
+
+    fn local(path: &Path) -> LocalRepository {
+        LocalRepository {
+            path: path.to_path_buf(),
+            relpath: path.to_path_buf(),
+            backend: VCSBackend::GitBackend,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn prune_keeps_local_work() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        init_repo(&origin);
+        let clone = |name: &str| {
+            git(dir.path(), &["clone", "-q", "origin", name]);
+            dir.path().join(name)
+        };
+
+        let clean = clone("clean");
+        assert_eq!(prune_blocker(&local(&clean)), None);
+
+        let untracked = clone("untracked");
+        fs::write(untracked.join("notes.txt"), "todo").unwrap();
+        assert_eq!(
+            prune_blocker(&local(&untracked)),
+            Some("uncommitted changes")
+        );
+        // git status exits 128 on a broken index, which must not read as clean
+        fs::write(untracked.join(".git/index"), "broken").unwrap();
+        assert_eq!(
+            prune_blocker(&local(&untracked)),
+            Some("failed to check status")
+        );
+
+        let stashed = clone("stashed");
+        fs::write(stashed.join("README"), "changed").unwrap();
+        git(&stashed, &["stash", "-q"]);
+        assert_eq!(prune_blocker(&local(&stashed)), Some("stashed changes"));
+
+        let unpushed = clone("unpushed");
+        git(&unpushed, &["commit", "-q", "--allow-empty", "-m", "wip"]);
+        assert_eq!(prune_blocker(&local(&unpushed)), Some("unpushed commits"));
+    }
+
+    #[test]
+    fn restore_revision() {
+        let dir = tempfile::tempdir().unwrap();
+        let origin = dir.path().join("origin");
+        init_repo(&origin);
+        let mut config: Config = Default::default();
+        config.repos.insert(
+            "default".to_owned(),
+            crate::config::RepositoryConfig {
+                root: dir.path().join("root").to_string_lossy().into_owned(),
+                ..Default::default()
+            },
+        );
+        let url = "https://example.com/owner/repo";
+        let path = parse_url(&config, None, url).unwrap().path;
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        git(
+            dir.path(),
+            &["clone", "-q", "origin", path.to_str().unwrap()],
+        );
+        let first = git(&path, &["rev-parse", "HEAD"]);
+        git(&path, &["commit", "-q", "--allow-empty", "-m", "second"]);
+        let entry = ManifestEntry {
+            url: url.to_owned(),
+            profile: None,
+            branch: None,
+            vcs: Some("git".to_owned()),
+            revision: Some(first.clone()),
+        };
+
+        assert!(matches!(
+            restore_entry(&config, &entry, &first),
+            Ok(Restored::CheckedOut)
+        ));
+        assert_eq!(git(&path, &["rev-parse", "HEAD"]), first);
+        assert!(matches!(
+            restore_entry(&config, &entry, &first),
+            Ok(Restored::Unchanged)
+        ));
+        assert!(matches!(
+            restore_entry(&config, &entry, &first[..7]),
+            Ok(Restored::Unchanged)
+        ));
+
+        let second = git(&path, &["rev-parse", "master"]);
+        fs::write(path.join("notes.txt"), "todo").unwrap();
+        let e = restore_entry(&config, &entry, &second).err().unwrap();
+        assert!(e.to_string().contains("uncommitted changes"));
+        // a status that can not be read is not a clean tree
+        fs::write(path.join(".git/index"), "broken").unwrap();
+        assert!(restore_entry(&config, &entry, &second).is_err());
+        assert_eq!(git(&path, &["rev-parse", "HEAD"]), first);
+    }
+}
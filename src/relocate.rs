@@ -0,0 +1,182 @@
+use crate::adopt::move_clone;
+use crate::config::Config;
+use crate::index::Index;
+use crate::local::{find_root, select, LocalRepository};
+use crate::remote::{check_collision, parse_url};
+use crate::tag::Metadata;
+use crate::utils::{is_empty_dir, real_path, remove_empty_parents};
+use anyhow::Result;
+use log::debug;
+use std::fs;
+
+#[derive(Debug, PartialEq)]
+enum Relocated {
+    InPlace,
+    Moved,
+    Skipped,
+}
+
+fn relocate_repo(
+    config: &Config<'_>,
+    index: &mut Index,
+    meta: &mut Metadata,
+    repo: &LocalRepository,
+    apply: bool,
+) -> Result<Relocated> {
+    // the index may still have the url before the rename
+    let remote = match repo.backend.remote_url(&repo.path)? {
+        Some(remote) => remote,
+        None => {
+            debug!("no remote url {:?}", repo.path);
+            return Ok(Relocated::InPlace);
+        }
+    };
+    let url = config.original_url(&remote);
+    let src = fs::canonicalize(&repo.path)?;
+    let moved = LocalRepository {
+        url: Some(url.clone()),
+        ..repo.clone()
+    };
+    // stay in the profile of the current root unless one is given
+    let profile = config.profile.or_else(|| moved.profile(config));
+    let opt = parse_url(config, profile, &url)?;
+    let dest = &opt.path;
+    if *dest == src {
+        return Ok(Relocated::InPlace);
+    }
+    if dest.starts_with(&src) {
+        return Err(anyhow::format_err!(
+            "{} can not be moved into itself {}",
+            src.display(),
+            dest.display()
+        ));
+    }
+    if dest.exists() && !is_empty_dir(dest) {
+        check_collision(config, &opt, &repo.backend)?;
+        println!(
+            "skip {} : {} is already cloned at {}",
+            src.display(),
+            url,
+            dest.display()
+        );
+        return Ok(Relocated::Skipped);
+    }
+    if !apply {
+        println!("{} -> {}", src.display(), dest.display());
+        return Ok(Relocated::Moved);
+    }
+
+    let src_root = find_root(config, &src).and_then(|root| real_path(root));
+    move_clone(config, index, &src, &opt, remote, &repo.backend, false)?;
+    if let Some(root) = src_root {
+        remove_empty_parents(&root, &src);
+    }
+    println!("{} : moved to {}", src.display(), dest.display());
+
+    // tags follow the repository key
+    let (old_key, new_key) = (repo.key(config), moved.key(config));
+    if old_key != new_key {
+        if let Some(tags) = meta.tags.remove(&old_key) {
+            meta.tags.entry(new_key).or_default().extend(tags);
+        }
+    }
+    Ok(Relocated::Moved)
+}
+
+///
+/// Finds repositories whose remote url no longer matches their directory,
+/// e.g. after a rename or a transfer, and moves them with `apply`.
+///
+pub fn relocate(config: &Config<'_>, apply: bool) -> Result<()> {
+    let repos = select(config)?;
+    let mut index = Index::load();
+    let mut meta = Metadata::load(&config.meta_path)?;
+    let tags = meta.tags.clone();
+    let (mut moved, mut in_place, mut skipped, mut failed) = (0, 0, 0, 0);
+    for repo in &repos {
+        match relocate_repo(config, &mut index, &mut meta, repo, apply) {
+            Ok(Relocated::Moved) => moved += 1,
+            Ok(Relocated::InPlace) => in_place += 1,
+            Ok(Relocated::Skipped) => skipped += 1,
+            Err(e) => {
+                eprintln!("failed {} : {}", repo.path.display(), e);
+                failed += 1;
+            }
+        }
+    }
+    if apply {
+        index.save()?;
+        if meta.tags != tags {
+            meta.save(&config.meta_path)?;
+        }
+        println!(
+            "relocated {}, in place {}, skipped {}, failed {}",
+            moved, in_place, skipped, failed
+        );
+    } else if moved > 0 {
+        println!(
+            "{} repositories to relocate, run with --apply to move them",
+            moved
+        );
+    }
+    if failed == 0 {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!(
+            "failed to relocate {} repositories",
+            failed
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RepositoryConfig;
+    use crate::utils::{git, init_repo};
+    use std::path::Path;
+
+    fn clone_at(path: &Path, url: &str) -> LocalRepository {
+        init_repo(path);
+        git(path, &["remote", "add", "origin", url]);
+        LocalRepository {
+            path: path.to_path_buf(),
+            relpath: path.to_path_buf(),
+            backend: crate::vcs::VCSBackend::GitBackend,
+            url: None,
+        }
+    }
+
+    #[test]
+    fn relocate_in_own_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        let mut config: Config = Default::default();
+        for (name, root) in [("default", root("a")), ("work", root("b"))] {
+            config.repos.insert(
+                name.to_owned(),
+                RepositoryConfig {
+                    root,
+                    ..Default::default()
+                },
+            );
+        }
+        let (mut index, mut meta) = (Index::default(), Metadata::default());
+
+        let placed = clone_at(
+            &dir.path().join("b/github.com/o/r"),
+            "https://github.com/o/r",
+        );
+        let relocated = relocate_repo(&config, &mut index, &mut meta, &placed, true).unwrap();
+        assert_eq!(relocated, Relocated::InPlace);
+
+        let renamed = clone_at(
+            &dir.path().join("b/github.com/o/old"),
+            "https://github.com/o/new",
+        );
+        let relocated = relocate_repo(&config, &mut index, &mut meta, &renamed, true).unwrap();
+        assert_eq!(relocated, Relocated::Moved);
+        assert!(dir.path().join("b/github.com/o/new/.git").is_dir());
+        assert!(!dir.path().join("a").exists());
+    }
+}
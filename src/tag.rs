@@ -0,0 +1,238 @@
+use crate::config::Config;
+use crate::local::{repositories, LocalRepository};
+use crate::remote::resolve_remote;
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::debug;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{from_str, to_string};
+
+///
+/// Metadata of repositories stored next to the config file.
+///
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    /// repository key (host/owner/repo) to tags
+    #[serde(default)]
+    pub tags: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl Metadata {
+    pub fn load(path: &Path) -> Result<Metadata> {
+        if !path.exists() {
+            return Ok(Default::default());
+        }
+        let s = fs::read_to_string(path)?;
+        from_str(&s).with_context(|| format!("failed parse toml. path: {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let s = to_string(self).context("failed serialize metadata")?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, s)?;
+        fs::rename(&tmp, path)?;
+        debug!("saved metadata {:?}", path);
+        Ok(())
+    }
+}
+
+/// Resolves the tags of repositories from the metadata and the static groups.
+pub struct Tags {
+    meta: Metadata,
+    groups: Vec<(String, GlobSet)>,
+}
+
+impl Tags {
+    pub fn load(config: &Config<'_>) -> Result<Tags> {
+        let meta = Metadata::load(&config.meta_path)?;
+        let mut groups = vec![];
+        for (name, patterns) in &config.groups {
+            let mut builder = GlobSetBuilder::new();
+            for pattern in patterns {
+                let glob = GlobBuilder::new(pattern)
+                    .literal_separator(true)
+                    .build()
+                    .with_context(|| format!("invalid pattern {} in group {}", pattern, name))?;
+                builder.add(glob);
+            }
+            groups.push((name.to_owned(), builder.build()?));
+        }
+        Ok(Tags { meta, groups })
+    }
+
+    pub fn of(&self, config: &Config<'_>, repo: &LocalRepository) -> BTreeSet<String> {
+        let key = repo.key(config);
+        let mut tags = self.meta.tags.get(&key).cloned().unwrap_or_default();
+        for (name, globs) in &self.groups {
+            if globs.is_match(&key) {
+                tags.insert(name.to_owned());
+            }
+        }
+        tags
+    }
+
+    /// Returns true if the repository has one of `tags`.
+    pub fn has_any(&self, config: &Config<'_>, repo: &LocalRepository, tags: &[&str]) -> bool {
+        let repo_tags = self.of(config, repo);
+        tags.iter().any(|t| repo_tags.contains(*t))
+    }
+}
+
+/// Finds the repository designated by a url, a key, a relpath or a path.
+fn resolve<'a>(
+    config: &Config<'_>,
+    repos: &'a [LocalRepository],
+    target: &str,
+) -> Result<&'a LocalRepository> {
+    let key = resolve_remote(config, config.profile, target)
+        .map(|remote| format!("{}/{}", remote.host, remote.path))
+        .ok();
+    let path = fs::canonicalize(target).unwrap_or_else(|_| PathBuf::from(target));
+    repos
+        .iter()
+        .find(|repo| {
+            Some(repo.key(config)) == key
+                || repo.key(config) == target
+                || repo.relpath == Path::new(target)
+                || repo.path == path
+        })
+        .with_context(|| format!("repository {} not found", target))
+}
+
+pub fn add(config: &Config<'_>, tag: &str, targets: &[&str]) -> Result<()> {
+    let repos = repositories(config)?;
+    let mut meta = Metadata::load(&config.meta_path)?;
+    for target in targets {
+        let repo = resolve(config, &repos, target)?;
+        meta.tags
+            .entry(repo.key(config))
+            .or_default()
+            .insert(tag.to_owned());
+        println!("tagged {} {}", repo.key(config), tag);
+    }
+    meta.save(&config.meta_path)
+}
+
+pub fn remove(config: &Config<'_>, tag: &str, targets: &[&str]) -> Result<()> {
+    let repos = repositories(config)?;
+    let mut meta = Metadata::load(&config.meta_path)?;
+    for target in targets {
+        let key = match resolve(config, &repos, target) {
+            Ok(repo) => repo.key(config),
+            // allow untagging repositories that no longer exist
+            Err(_) => target.to_string(),
+        };
+        if let Some(tags) = meta.tags.get_mut(&key) {
+            if tags.remove(tag) {
+                println!("untagged {} {}", key, tag);
+            }
+            if tags.is_empty() {
+                meta.tags.remove(&key);
+            }
+        }
+    }
+    meta.save(&config.meta_path)
+}
+
+pub fn list(config: &Config<'_>, target: Option<&str>) -> Result<()> {
+    let repos = repositories(config)?;
+    let tags = Tags::load(config)?;
+    let repos: Vec<&LocalRepository> = match target {
+        Some(target) => vec![resolve(config, &repos, target)?],
+        None => repos.iter().collect(),
+    };
+    for repo in repos {
+        let repo_tags = tags.of(config, repo);
+        if !repo_tags.is_empty() {
+            let repo_tags: Vec<String> = repo_tags.into_iter().collect();
+            println!("{}\t{}", repo.key(config), repo_tags.join(","));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vcs::VCSBackend;
+
+    #[test]
+    fn tags_of_repository() {
+        let mut config = Config {
+            meta_path: PathBuf::from("/nonexistent/rrc-meta.toml"),
+            ..Default::default()
+        };
+        config
+            .groups
+            .insert("work".to_owned(), vec!["github.com/mycompany/*".to_owned()]);
+        let mut tags = Tags::load(&config).unwrap();
+        tags.meta.tags.insert(
+            "github.com/mycompany/api".to_owned(),
+            vec!["backend".to_owned()].into_iter().collect(),
+        );
+        let repo = LocalRepository {
+            path: PathBuf::from("/repos/github.com/mycompany/api"),
+            relpath: PathBuf::from("github.com/mycompany/api"),
+            backend: VCSBackend::GitBackend,
+            url: Some("https://github.com/mycompany/api".to_owned()),
+        };
+        let expected: BTreeSet<String> = vec!["backend".to_owned(), "work".to_owned()]
+            .into_iter()
+            .collect();
+        assert_eq!(tags.of(&config, &repo), expected);
+        assert!(tags.has_any(&config, &repo, &["work"]));
+        assert!(!tags.has_any(&config, &repo, &["frontend"]));
+
+        let other = LocalRepository {
+            path: PathBuf::from("/repos/github.com/mycompany/api/sub"),
+            relpath: PathBuf::from("github.com/mycompany/api/sub"),
+            url: None,
+            ..repo
+        };
+        assert!(tags.of(&config, &other).is_empty());
+    }
+
+    #[test]
+    fn tags_in_layout() {
+        let mut config = Config {
+            meta_path: PathBuf::from("/nonexistent/rrc-meta.toml"),
+            ..Default::default()
+        };
+        let profile = config.repos.get_mut("default").unwrap();
+        profile.root = "/repos".to_owned();
+        profile.layout = "src/{host}/{owner}/{repo}".to_owned();
+        config
+            .groups
+            .insert("work".to_owned(), vec!["github.com/mycompany/*".to_owned()]);
+        let tags = Tags::load(&config).unwrap();
+        let repo = LocalRepository {
+            path: PathBuf::from("/repos/src/github.com/mycompany/api"),
+            relpath: PathBuf::from("src/github.com/mycompany/api"),
+            backend: VCSBackend::GitBackend,
+            url: None,
+        };
+        assert_eq!(repo.key(&config), "github.com/mycompany/api");
+        assert!(tags.has_any(&config, &repo, &["work"]));
+    }
+
+    #[test]
+    fn save_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config").join("rrc-meta.toml");
+        let mut meta = Metadata::default();
+        meta.tags.insert(
+            "github.com/mycompany/api".to_owned(),
+            vec!["backend".to_owned()].into_iter().collect(),
+        );
+        meta.save(&path).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+        let loaded = Metadata::load(&path).unwrap();
+        assert_eq!(loaded.tags, meta.tags);
+    }
+}
@@ -1,7 +1,11 @@
 mod input;
 mod path;
 mod process;
+#[cfg(test)]
+mod testing;
 
 pub use input::confirm;
-pub use path::expand_home;
-pub use process::{chdir, run, run_silently, run_with_work_dir};
+pub use path::{copy_dir, is_empty_dir, real_path, remove_empty_parents};
+pub use process::{chdir, run, run_checked, run_output, run_silently, run_with_work_dir};
+#[cfg(test)]
+pub use testing::{git, init_repo};
@@ -1,3 +1,5 @@
+use anyhow::Result;
+use std::fs;
 use std::path::{Path, PathBuf};
 
 pub fn expand_home(path: &str) -> Option<PathBuf> {
@@ -19,3 +21,85 @@ pub fn expand_home(path: &str) -> Option<PathBuf> {
         Some(path.to_path_buf())
     }
 }
+
+/// Expands `~` and resolves symlinks if the path exists.
+pub fn real_path(path: &str) -> Option<PathBuf> {
+    let path = expand_home(path)?;
+    Some(fs::canonicalize(&path).unwrap_or(path))
+}
+
+/// Returns true for a directory without entries.
+pub fn is_empty_dir(path: &Path) -> bool {
+    fs::read_dir(path)
+        .map(|mut entries| entries.next().is_none())
+        .unwrap_or(false)
+}
+
+/// Removes the directories left empty under `root` by a move.
+pub fn remove_empty_parents(root: &Path, path: &Path) {
+    for parent in path.ancestors().skip(1) {
+        if parent == root || !parent.starts_with(root) || fs::remove_dir(parent).is_err() {
+            break;
+        }
+    }
+}
+
+/// Copies a directory tree. Symlinks are copied as links.
+pub fn copy_dir(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst)?;
+    for entry in fs::read_dir(src)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+        let target = dst.join(entry.file_name());
+        if file_type.is_symlink() {
+            let link = fs::read_link(entry.path())?;
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(link, &target)?;
+            #[cfg(not(unix))]
+            return Err(anyhow::format_err!("can not copy symlink {:?}", link));
+        } else if file_type.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remove_empty_parents_under_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        let src = root.join("example.com/old-owner/repo");
+        fs::create_dir_all(&src).unwrap();
+        fs::create_dir_all(root.join("example.com/other")).unwrap();
+        fs::remove_dir(&src).unwrap();
+        remove_empty_parents(&root, &src);
+        assert!(!root.join("example.com/old-owner").exists());
+        assert!(root.join("example.com/other").exists());
+        assert!(root.exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn copy_dir_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        fs::create_dir_all(src.join("a/b")).unwrap();
+        fs::write(src.join("a/b/file"), "content").unwrap();
+        std::os::unix::fs::symlink("a/b/file", src.join("link")).unwrap();
+
+        let dst = dir.path().join("dst");
+        copy_dir(&src, &dst).unwrap();
+        assert_eq!(fs::read_to_string(dst.join("a/b/file")).unwrap(), "content");
+        assert_eq!(
+            fs::read_link(dst.join("link")).unwrap(),
+            Path::new("a/b/file")
+        );
+        assert!(src.join("a/b/file").exists());
+    }
+}
@@ -1,9 +1,11 @@
 use anyhow::Result;
 use std::env;
+use std::ffi::OsStr;
+use std::path::Path;
 use std::process::{Command, Stdio};
 
 pub fn run_silently(cmd: &[&str]) -> Result<bool> {
-    let mut cmd = Command::new(&cmd[0])
+    let mut cmd = Command::new(cmd[0])
         .args(&cmd[1..])
         .stdout(Stdio::null())
         .stderr(Stdio::null())
@@ -13,7 +15,7 @@ pub fn run_silently(cmd: &[&str]) -> Result<bool> {
     Ok(status.success())
 }
 
-pub fn run(cmd: &[&str]) -> Result<bool> {
+pub fn run<S: AsRef<OsStr>>(cmd: &[S]) -> Result<bool> {
     let mut cmd = Command::new(&cmd[0])
         .args(&cmd[1..])
         .stdout(Stdio::inherit())
@@ -23,8 +25,8 @@ pub fn run(cmd: &[&str]) -> Result<bool> {
     Ok(status.success())
 }
 
-pub fn run_with_work_dir(cmd: &[&str], dir: &str) -> Result<bool> {
-    let mut cmd = Command::new(&cmd[0])
+pub fn run_with_work_dir(cmd: &[&str], dir: &Path) -> Result<bool> {
+    let mut cmd = Command::new(cmd[0])
         .args(&cmd[1..])
         .current_dir(dir)
         .stdout(Stdio::inherit())
@@ -34,7 +36,42 @@ pub fn run_with_work_dir(cmd: &[&str], dir: &str) -> Result<bool> {
     Ok(status.success())
 }
 
-pub fn chdir(dir: &str) -> Result<bool> {
+pub fn run_output(cmd: &[&str], dir: &Path) -> Result<Option<String>> {
+    let output = Command::new(cmd[0])
+        .args(&cmd[1..])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let out = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+    if out.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(out))
+    }
+}
+
+/// Like `run_output` but a non-zero exit is an error, for checks that must not pass silently.
+pub fn run_checked(cmd: &[&str], dir: &Path) -> Result<String> {
+    let output = Command::new(cmd[0])
+        .args(&cmd[1..])
+        .current_dir(dir)
+        .stdin(Stdio::null())
+        .output()?;
+    if !output.status.success() {
+        return Err(anyhow::format_err!(
+            "{} failed: {}",
+            cmd.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+pub fn chdir(dir: &Path) -> Result<bool> {
     if let Ok(shell) = env::var("SHELL") {
         run_with_work_dir(&[&shell], dir)
     } else {
@@ -61,4 +98,19 @@ mod tests {
     fn run1() {
         run(&["ls", "-al"]).unwrap();
     }
+
+    #[test]
+    fn run_output1() {
+        let out = run_output(&["echo", "hello"], Path::new(".")).unwrap();
+        assert_eq!(out, Some("hello".to_owned()));
+        let out = run_output(&["false"], Path::new(".")).unwrap();
+        assert_eq!(out, None);
+    }
+
+    #[test]
+    fn run_checked1() {
+        let out = run_checked(&["echo", "hello"], Path::new(".")).unwrap();
+        assert_eq!(out, "hello");
+        assert!(run_checked(&["false"], Path::new(".")).is_err());
+    }
 }
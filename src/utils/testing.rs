@@ -0,0 +1,35 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Runs git in `dir` for a test, panicking on failure.
+pub fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(["-c", "user.name=rrc", "-c", "user.email=rrc@example.com"])
+        .args([
+            "-c",
+            "commit.gpgsign=false",
+            "-c",
+            "init.defaultBranch=master",
+        ])
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "git {:?}: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_owned()
+}
+
+/// Creates a git repository at `path` with one commit.
+pub fn init_repo(path: &Path) {
+    fs::create_dir_all(path).unwrap();
+    git(path, &["init", "-q"]);
+    fs::write(path.join("README"), "rrc\n").unwrap();
+    git(path, &["add", "README"]);
+    git(path, &["commit", "-q", "-m", "init"]);
+}
@@ -1,6 +1,8 @@
 use super::{VCSBackend, VCSOption};
-use crate::utils::{run, run_silently, run_with_work_dir};
+use crate::utils::{run, run_checked, run_output, run_silently, run_with_work_dir};
 use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::path::Path;
 use url::Url;
 
 pub fn from_str(s: &str) -> Result<VCSBackend> {
@@ -15,7 +17,7 @@ pub fn from_str(s: &str) -> Result<VCSBackend> {
         }
     }
 
-    match run_silently(&["git", "ls-remote", s]) {
+    match run_silently(&["git", "ls-remote", "--", s]) {
         Ok(true) => Ok(VCSBackend::GitBackend),
         Ok(false) => Err(anyhow::format_err!("not git repository")),
         Err(e) => Err(e),
@@ -24,19 +26,88 @@ pub fn from_str(s: &str) -> Result<VCSBackend> {
 
 pub fn get_repository(option: &VCSOption) -> Result<()> {
     let url = option.url.clone().context("failed clone url")?;
-    match run(&["git", "clone", &url, &option.path]) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e),
+    let mut cmd = vec![OsStr::new("git"), OsStr::new("clone")];
+    if let Some(branch) = &option.branch {
+        cmd.push(OsStr::new("--branch"));
+        cmd.push(OsStr::new(branch));
+    }
+    cmd.push(OsStr::new("--"));
+    cmd.push(OsStr::new(&url));
+    cmd.push(option.path.as_os_str());
+    if run(&cmd)? {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!("failed clone {}", url))
     }
 }
 
 pub fn update(option: &VCSOption) -> Result<()> {
-    match run_with_work_dir(&["git", "pull", "--ff-only"], &option.path) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e),
+    if run_with_work_dir(&["git", "pull", "--ff-only"], &option.path)? {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!("failed pull {}", option.path.display()))
+    }
+}
+
+pub fn remote_url(path: &Path) -> Result<Option<String>> {
+    run_output(&["git", "config", "--get", "remote.origin.url"], path)
+}
+
+/// Returns None on a detached HEAD.
+pub fn current_branch(path: &Path) -> Result<Option<String>> {
+    let out = run_output(&["git", "rev-parse", "--abbrev-ref", "HEAD"], path)?;
+    Ok(out.map(|s| s.trim().to_owned()).filter(|s| s != "HEAD"))
+}
+
+pub fn current_revision(path: &Path) -> Result<Option<String>> {
+    let out = run_output(&["git", "rev-parse", "--verify", "-q", "HEAD"], path)?;
+    Ok(out.map(|s| s.trim().to_owned()))
+}
+
+/// Checks out the commit on a detached HEAD, fetching it if missing.
+pub fn checkout(path: &Path, revision: &str) -> Result<()> {
+    let object = format!("{}^{{commit}}", revision);
+    let exists = run_output(&["git", "cat-file", "-t", &object], path)?;
+    if exists.is_none() && !run_with_work_dir(&["git", "fetch", "-q", "origin"], path)? {
+        return Err(anyhow::format_err!("failed fetch {}", path.display()));
+    }
+    if run_with_work_dir(&["git", "checkout", "-q", "--detach", revision], path)? {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!("failed checkout {}", revision))
     }
 }
 
+/// Returns true if some local commits are not in any remote branch.
+pub fn has_unpushed(path: &Path) -> Result<bool> {
+    let out = run_checked(
+        &[
+            "git",
+            "log",
+            "--branches",
+            "--not",
+            "--remotes",
+            "--oneline",
+        ],
+        path,
+    )?;
+    Ok(!out.is_empty())
+}
+
+pub fn has_stash(path: &Path) -> Result<bool> {
+    Ok(!run_checked(&["git", "stash", "list"], path)?.is_empty())
+}
+
+pub fn is_dirty(path: &Path) -> Result<bool> {
+    Ok(!run_checked(&["git", "status", "--porcelain"], path)?.is_empty())
+}
+
+/// Returns the time of the last commit in seconds since the epoch.
+pub fn last_commit_time(path: &Path) -> Result<Option<u64>> {
+    let out = run_output(&["git", "log", "-1", "--format=%ct"], path)?;
+    Ok(out.and_then(|s| s.split_whitespace().next().and_then(|t| t.parse().ok())))
+}
+
 pub fn from_path(path: &str) -> Option<VCSBackend> {
     if path == ".git" {
         Some(VCSBackend::GitBackend)
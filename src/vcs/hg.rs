@@ -1,9 +1,11 @@
 use super::{VCSBackend, VCSOption};
-use crate::utils::{run, run_silently, run_with_work_dir};
+use crate::utils::{run, run_checked, run_output, run_silently, run_with_work_dir};
 use anyhow::{Context, Result};
+use std::ffi::OsStr;
+use std::path::Path;
 
 pub fn from_str(s: &str) -> Result<VCSBackend> {
-    match run_silently(&["hg", "identify", s]) {
+    match run_silently(&["hg", "identify", "--", s]) {
         Ok(true) => Ok(VCSBackend::MercurialBackend),
         Ok(false) => Err(anyhow::format_err!("not hg repository")),
         Err(e) => Err(e),
@@ -12,19 +14,82 @@ pub fn from_str(s: &str) -> Result<VCSBackend> {
 
 pub fn get_repository(option: &VCSOption) -> Result<()> {
     let url = option.url.clone().context("failed clone url")?;
-    match run(&["hg", "clone", &url, &option.path]) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e),
+    let mut cmd = vec![OsStr::new("hg"), OsStr::new("clone")];
+    if let Some(branch) = &option.branch {
+        cmd.push(OsStr::new("--updaterev"));
+        cmd.push(OsStr::new(branch));
+    }
+    cmd.push(OsStr::new("--"));
+    cmd.push(OsStr::new(&url));
+    cmd.push(option.path.as_os_str());
+    if run(&cmd)? {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!("failed clone {}", url))
     }
 }
 
 pub fn update(option: &VCSOption) -> Result<()> {
-    match run_with_work_dir(&["hg", "pull", "--update"], &option.path) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(e),
+    if run_with_work_dir(&["hg", "pull", "--update"], &option.path)? {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!("failed pull {}", option.path.display()))
+    }
+}
+
+pub fn remote_url(path: &Path) -> Result<Option<String>> {
+    run_output(&["hg", "paths", "default"], path)
+}
+
+pub fn current_branch(path: &Path) -> Result<Option<String>> {
+    let out = run_output(&["hg", "branch"], path)?;
+    Ok(out.map(|s| s.trim().to_owned()))
+}
+
+pub fn current_revision(path: &Path) -> Result<Option<String>> {
+    let out = run_output(&["hg", "log", "-r", ".", "--template", "{node}"], path)?;
+    Ok(out
+        .map(|s| s.trim().to_owned())
+        .filter(|node| node.chars().any(|c| c != '0')))
+}
+
+/// Updates to the changeset, pulling it if missing.
+pub fn checkout(path: &Path, revision: &str) -> Result<()> {
+    let exists = run_output(&["hg", "log", "-r", revision, "--template", "x"], path)?;
+    if exists.is_none() && !run_with_work_dir(&["hg", "pull", "-q"], path)? {
+        return Err(anyhow::format_err!("failed pull {}", path.display()));
+    }
+    if run_with_work_dir(&["hg", "update", "-q", "-r", revision], path)? {
+        Ok(())
+    } else {
+        Err(anyhow::format_err!("failed update {}", revision))
     }
 }
 
+/// Returns true if some changesets are still draft, i.e. not pushed.
+pub fn has_unpushed(path: &Path) -> Result<bool> {
+    let out = run_checked(&["hg", "log", "-r", "draft()", "--template", "x"], path)?;
+    Ok(!out.is_empty())
+}
+
+/// Shelves need the shelve extension, so they are listed only if it is enabled.
+pub fn has_stash(path: &Path) -> Result<bool> {
+    Ok(run_output(&["hg", "shelve", "--list"], path)?.is_some())
+}
+
+pub fn is_dirty(path: &Path) -> Result<bool> {
+    Ok(!run_checked(&["hg", "status"], path)?.is_empty())
+}
+
+/// Returns the time of the last commit in seconds since the epoch.
+pub fn last_commit_time(path: &Path) -> Result<Option<u64>> {
+    let out = run_output(
+        &["hg", "log", "-l", "1", "--template", "{date|hgdate}"],
+        path,
+    )?;
+    Ok(out.and_then(|s| s.split_whitespace().next().and_then(|t| t.parse().ok())))
+}
+
 pub fn from_path(path: &str) -> Option<VCSBackend> {
     if path == ".hg" {
         Some(VCSBackend::MercurialBackend)
@@ -1,28 +1,39 @@
 mod git;
 mod hg;
 
-use anyhow::Result;
+use anyhow::{Error, Result};
 use serde_derive::Deserialize;
 use std::fmt::Debug;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct VCSOption {
     pub url: Option<String>,
-    pub path: String,
+    pub path: PathBuf,
     pub host: Option<String>,
     // pub recursive: bool,
     // pub shallow: bool,
     // pub silent: bool,
-    // pub branch: String,
+    /// branch checked out after clone
+    pub branch: Option<String>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub enum VCSBackend {
+    #[serde(rename = "git")]
     GitBackend,
+    #[serde(rename = "hg", alias = "mercurial")]
     MercurialBackend,
 }
 
 impl VCSBackend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VCSBackend::GitBackend => "git",
+            VCSBackend::MercurialBackend => "hg",
+        }
+    }
     pub fn get_repository(&self, opt: &VCSOption) -> Result<()> {
         match self {
             VCSBackend::GitBackend => git::get_repository(opt),
@@ -35,6 +46,67 @@ impl VCSBackend {
             VCSBackend::MercurialBackend => hg::update(opt),
         }
     }
+    pub fn is_dirty(&self, path: &Path) -> Result<bool> {
+        match self {
+            VCSBackend::GitBackend => git::is_dirty(path),
+            VCSBackend::MercurialBackend => hg::is_dirty(path),
+        }
+    }
+    pub fn last_commit_time(&self, path: &Path) -> Result<Option<u64>> {
+        match self {
+            VCSBackend::GitBackend => git::last_commit_time(path),
+            VCSBackend::MercurialBackend => hg::last_commit_time(path),
+        }
+    }
+    pub fn current_branch(&self, path: &Path) -> Result<Option<String>> {
+        match self {
+            VCSBackend::GitBackend => git::current_branch(path),
+            VCSBackend::MercurialBackend => hg::current_branch(path),
+        }
+    }
+    pub fn current_revision(&self, path: &Path) -> Result<Option<String>> {
+        match self {
+            VCSBackend::GitBackend => git::current_revision(path),
+            VCSBackend::MercurialBackend => hg::current_revision(path),
+        }
+    }
+    pub fn checkout(&self, path: &Path, revision: &str) -> Result<()> {
+        match self {
+            VCSBackend::GitBackend => git::checkout(path, revision),
+            VCSBackend::MercurialBackend => hg::checkout(path, revision),
+        }
+    }
+    pub fn has_unpushed(&self, path: &Path) -> Result<bool> {
+        match self {
+            VCSBackend::GitBackend => git::has_unpushed(path),
+            VCSBackend::MercurialBackend => hg::has_unpushed(path),
+        }
+    }
+    /// Returns true if changes are stashed or shelved.
+    pub fn has_stash(&self, path: &Path) -> Result<bool> {
+        match self {
+            VCSBackend::GitBackend => git::has_stash(path),
+            VCSBackend::MercurialBackend => hg::has_stash(path),
+        }
+    }
+    pub fn remote_url(&self, path: &Path) -> Result<Option<String>> {
+        match self {
+            VCSBackend::GitBackend => git::remote_url(path),
+            VCSBackend::MercurialBackend => hg::remote_url(path),
+        }
+    }
+}
+
+impl FromStr for VCSBackend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<VCSBackend> {
+        match s {
+            "git" => Ok(VCSBackend::GitBackend),
+            "hg" => Ok(VCSBackend::MercurialBackend),
+            _ => Err(anyhow::format_err!("unknown vcs backend {}", s)),
+        }
+    }
 }
 
 pub fn detect_vcs(url: &str) -> Result<VCSBackend> {
@@ -48,11 +120,5 @@ pub fn detect_vcs(url: &str) -> Result<VCSBackend> {
 }
 
 pub fn detect_vcs_from_path(path: &str) -> Option<VCSBackend> {
-    if let Some(backend) = git::from_path(path) {
-        Some(backend)
-    } else if let Some(backend) = hg::from_path(path) {
-        Some(backend)
-    } else {
-        None
-    }
+    git::from_path(path).or_else(|| hg::from_path(path))
 }
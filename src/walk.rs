@@ -0,0 +1,420 @@
+use crate::index;
+use crate::local::LocalRepository;
+use crate::utils::real_path;
+use crate::vcs::{detect_vcs_from_path, VCSBackend};
+use anyhow::{Context, Result};
+use async_std::task;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use log::debug;
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+pub const IGNORE_FILE: &str = ".rrcignore";
+pub const DEFAULT_MAX_DEPTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WalkOptions {
+    /// how deep directories are read below the root
+    pub max_depth: usize,
+    /// report repositories nested inside another repository
+    pub nested: bool,
+    /// glob patterns of directories to skip
+    pub ignore: Vec<String>,
+}
+
+impl Default for WalkOptions {
+    fn default() -> Self {
+        WalkOptions {
+            max_depth: DEFAULT_MAX_DEPTH,
+            nested: false,
+            ignore: vec![],
+        }
+    }
+}
+
+impl WalkOptions {
+    /// A string identifying the options that affect the result of a walk.
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "max_depth={} nested={} ignore={:?}",
+            self.max_depth, self.nested, self.ignore
+        )
+    }
+}
+
+/// The result of a walk.
+pub struct Walked {
+    pub repos: Vec<LocalRepository>,
+    /// directories that have been read, with their mtimes; repositories only when
+    /// walking nested ones
+    pub dirs: BTreeMap<PathBuf, u64>,
+}
+
+struct Walker {
+    root: PathBuf,
+    max_depth: usize,
+    nested: bool,
+    ignore: GlobSet,
+    visited: Mutex<HashSet<PathBuf>>,
+    repos: Mutex<Vec<LocalRepository>>,
+    dirs: Mutex<BTreeMap<PathBuf, u64>>,
+}
+
+/// Builds a matcher from ignore patterns.
+/// Patterns without a slash match a directory name at any depth,
+/// others match the path relative to the root.
+fn build_ignore(patterns: &[String]) -> Result<GlobSet> {
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        let pattern = pattern.trim_end_matches('/');
+        let pattern = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_owned()
+        } else {
+            format!("**/{}", pattern)
+        };
+        let glob = GlobBuilder::new(&pattern)
+            .literal_separator(true)
+            .build()
+            .with_context(|| format!("invalid ignore pattern {}", pattern))?;
+        builder.add(glob);
+    }
+    Ok(builder.build()?)
+}
+
+/// Reads ignore patterns from the `.rrcignore` file of the root.
+fn read_ignore_file(root: &Path) -> Vec<String> {
+    match fs::read_to_string(root.join(IGNORE_FILE)) {
+        Ok(s) => s
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(|l| l.to_owned())
+            .collect(),
+        Err(_) => vec![],
+    }
+}
+
+impl Walker {
+    fn record(&self, dir: &Path) {
+        if let Some(time) = index::mtime(dir) {
+            let mut dirs = self.dirs.lock().unwrap();
+            dirs.insert(dir.to_path_buf(), time);
+        }
+    }
+
+    fn is_ignored(&self, path: &Path) -> bool {
+        match path.strip_prefix(&self.root) {
+            Ok(relpath) => self.ignore.is_match(relpath),
+            Err(_) => false,
+        }
+    }
+
+    /// A symlink to a directory inside the root, which is walked by its real path.
+    fn is_alias(&self, path: &Path) -> bool {
+        let is_link = fs::symlink_metadata(path)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+        is_link
+            && fs::canonicalize(path)
+                .map(|real| real.starts_with(&self.root))
+                .unwrap_or(false)
+    }
+
+    /// Returns false if the directory (or the target of a symlink) was walked already.
+    fn first_visit(&self, path: &Path) -> bool {
+        match fs::canonicalize(path) {
+            Ok(real) => {
+                let mut visited = self.visited.lock().unwrap();
+                visited.insert(real)
+            }
+            Err(e) => {
+                debug!("{} path:{:?}", e, path);
+                false
+            }
+        }
+    }
+
+    fn push(&self, dir: &Path, backend: VCSBackend) -> Result<()> {
+        let relpath = dir
+            .strip_prefix(&self.root)
+            .with_context(|| format!("{:?} is not in {:?}", dir, self.root))?;
+        let mut repos = self.repos.lock().unwrap();
+        repos.push(LocalRepository {
+            path: dir.to_path_buf(),
+            relpath: relpath.to_path_buf(),
+            backend,
+            url: None,
+        });
+        Ok(())
+    }
+
+    /// Reads `dir`, records it as a repository or a plain directory and walks its sub directories.
+    fn walk_dir(&self, dir: &Path, depth: usize) -> Result<()> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug!("{} path:{:?}", e, dir);
+                return Ok(());
+            }
+        };
+        let mut backend = None;
+        let mut subdirs = vec![];
+        for entry in entries {
+            let entry = entry?;
+            let file_name = entry.file_name();
+            if let Some(b) = file_name.to_str().and_then(detect_vcs_from_path) {
+                // .git may be a file in submodules and worktrees
+                backend = Some(b);
+                continue;
+            }
+            let file_type = entry.file_type()?;
+            let path = entry.path();
+            if file_type.is_dir()
+                || (file_type.is_symlink()
+                    && fs::metadata(&path).map(|m| m.is_dir()).unwrap_or(false))
+            {
+                subdirs.push(path);
+            }
+        }
+
+        match backend {
+            Some(backend) if depth > 0 => {
+                self.push(dir, backend)?;
+                if !self.nested {
+                    return Ok(());
+                }
+                // a repository cloned inside this one changes its mtime
+                self.record(dir);
+            }
+            _ => self.record(dir),
+        }
+
+        if depth >= self.max_depth {
+            return Ok(());
+        }
+        for path in subdirs {
+            if self.is_ignored(&path) {
+                debug!("ignore {:?}", path);
+                continue;
+            }
+            if self.is_alias(&path) {
+                debug!("skip alias {:?}", path);
+                continue;
+            }
+            if !self.first_visit(&path) {
+                debug!("skip visited {:?}", path);
+                continue;
+            }
+            self.walk_dir(&path, depth + 1)?;
+        }
+        Ok(())
+    }
+}
+
+/// Walks `root` and collects the repositories below it.
+/// Each top-level directory is walked in parallel. Symlinks to directories
+/// inside the root are skipped, so those repositories are reported by their
+/// real path; symlinks leaving the root are walked and reported under the link.
+/// Paths are reported under the canonicalized root, so `relpath` is correct
+/// even when the root itself is a symlink.
+pub fn walk(root: &str, opts: &WalkOptions) -> Result<Walked> {
+    let root_path = real_path(root).with_context(|| format!("failed expand root {}", root))?;
+    let root_path = root_path.as_path();
+    let mut ignore = opts.ignore.clone();
+    ignore.extend(read_ignore_file(root_path));
+    let walker = Arc::new(Walker {
+        root: root_path.to_path_buf(),
+        max_depth: opts.max_depth,
+        nested: opts.nested,
+        ignore: build_ignore(&ignore)?,
+        visited: Mutex::new(HashSet::new()),
+        repos: Mutex::new(vec![]),
+        dirs: Mutex::new(BTreeMap::new()),
+    });
+
+    let entries = match fs::read_dir(root_path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            debug!("{} path:{:?}", e, root);
+            return Ok(Walked {
+                repos: vec![],
+                dirs: BTreeMap::new(),
+            });
+        }
+    };
+    walker.first_visit(root_path);
+    walker.record(root_path);
+    walker.record(&root_path.join(IGNORE_FILE));
+
+    let mut futures: Vec<task::JoinHandle<Result<()>>> = vec![];
+    if opts.max_depth > 0 {
+        for entry in entries {
+            let path = entry?.path();
+            if !fs::metadata(&path).map(|m| m.is_dir()).unwrap_or(false) {
+                continue;
+            }
+            if walker.is_ignored(&path) || walker.is_alias(&path) || !walker.first_visit(&path) {
+                continue;
+            }
+            let walker = Arc::clone(&walker);
+            let f = task::spawn(async move { walker.walk_dir(&path, 1) });
+            futures.push(f);
+        }
+    }
+    for f in futures {
+        task::block_on(f)?;
+    }
+
+    let walker =
+        Arc::try_unwrap(walker).map_err(|_| anyhow::format_err!("failed walk {}", root))?;
+    let mut repos = walker.repos.into_inner().unwrap();
+    repos.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(Walked {
+        repos,
+        dirs: walker.dirs.into_inner().unwrap(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{canonicalize, create_dir_all, write};
+
+    fn relpaths(walked: &Walked) -> Vec<String> {
+        let mut repos: Vec<String> = walked
+            .repos
+            .iter()
+            .map(|r| r.relpath.to_str().unwrap().to_owned())
+            .collect();
+        repos.sort();
+        repos
+    }
+
+    #[test]
+    fn walk_repositories() {
+        let _ = env_logger::try_init();
+        let tmp = tempfile::tempdir().unwrap();
+        let root = canonicalize(tmp.path()).unwrap();
+        create_dir_all(root.join("github.com/foo/bar/.git")).unwrap();
+        create_dir_all(root.join("github.com/foo/baz/.hg")).unwrap();
+        create_dir_all(root.join("gitlab.com/a/b/c/d/.git")).unwrap();
+        create_dir_all(root.join("flat/.git")).unwrap();
+        create_dir_all(root.join("example.com/empty")).unwrap();
+        create_dir_all(root.join("github.com/foo/bar/vendor/lib/.git")).unwrap();
+        write(root.join("github.com/foo/bar/sub"), "").unwrap();
+        create_dir_all(root.join("github.com/foo/bar/module")).unwrap();
+        write(
+            root.join("github.com/foo/bar/module/.git"),
+            "gitdir: ../.git/modules",
+        )
+        .unwrap();
+
+        let walked = walk(root.to_str().unwrap(), &Default::default()).unwrap();
+        assert_eq!(
+            relpaths(&walked),
+            vec![
+                "flat",
+                "github.com/foo/bar",
+                "github.com/foo/baz",
+                "gitlab.com/a/b/c/d"
+            ]
+        );
+        assert!(walked.dirs.contains_key(&root.join("example.com/empty")));
+        assert!(!walked.dirs.contains_key(&root.join("github.com/foo/bar")));
+
+        let opts = WalkOptions {
+            nested: true,
+            ..Default::default()
+        };
+        let walked = walk(root.to_str().unwrap(), &opts).unwrap();
+        assert_eq!(
+            relpaths(&walked),
+            vec![
+                "flat",
+                "github.com/foo/bar",
+                "github.com/foo/bar/module",
+                "github.com/foo/bar/vendor/lib",
+                "github.com/foo/baz",
+                "gitlab.com/a/b/c/d"
+            ]
+        );
+        // a clone made inside a repository makes the index stale
+        assert!(walked.dirs.contains_key(&root.join("github.com/foo/bar")));
+
+        let opts = WalkOptions {
+            max_depth: 3,
+            ..Default::default()
+        };
+        let walked = walk(root.to_str().unwrap(), &opts).unwrap();
+        assert_eq!(
+            relpaths(&walked),
+            vec!["flat", "github.com/foo/bar", "github.com/foo/baz"]
+        );
+    }
+
+    #[test]
+    fn walk_ignore() {
+        let tmp = tempfile::tempdir().unwrap();
+        let root = canonicalize(tmp.path()).unwrap();
+        create_dir_all(root.join("github.com/foo/bar/.git")).unwrap();
+        create_dir_all(root.join("github.com/foo/archive/.git")).unwrap();
+        create_dir_all(root.join("gitlab.com/foo/bar/.git")).unwrap();
+        create_dir_all(root.join("tmp/x/.git")).unwrap();
+        write(root.join(IGNORE_FILE), "# comment\ntmp\n").unwrap();
+
+        let opts = WalkOptions {
+            ignore: vec!["gitlab.com/*/bar".to_owned(), "archive".to_owned()],
+            ..Default::default()
+        };
+        let walked = walk(root.to_str().unwrap(), &opts).unwrap();
+        assert_eq!(relpaths(&walked), vec!["github.com/foo/bar"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_symlink_cycle() {
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let root = canonicalize(tmp.path()).unwrap();
+        create_dir_all(root.join("github.com/foo/bar/.git")).unwrap();
+        symlink(root.join("github.com"), root.join("github.com/foo/loop")).unwrap();
+        symlink(root.join("github.com/foo/bar"), root.join("alias")).unwrap();
+        symlink(root.join("github.com/foo"), root.join("a")).unwrap();
+        // followed out of the root, reported under the link
+        let outside = tempfile::tempdir().unwrap();
+        create_dir_all(outside.path().join("baz/.git")).unwrap();
+        symlink(outside.path(), root.join("external")).unwrap();
+
+        for _ in 0..10 {
+            let walked = walk(root.to_str().unwrap(), &Default::default()).unwrap();
+            assert_eq!(
+                relpaths(&walked),
+                vec!["external/baz", "github.com/foo/bar"]
+            );
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn walk_symlink_root_and_non_utf8() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+        use std::os::unix::fs::symlink;
+
+        let tmp = tempfile::tempdir().unwrap();
+        let real = tmp.path().join("real");
+        let name = OsStr::from_bytes(b"caf\xe9");
+        create_dir_all(real.join("example.com").join(name).join(".git")).unwrap();
+        symlink(&real, tmp.path().join("link")).unwrap();
+
+        let root = tmp.path().join("link");
+        let walked = walk(root.to_str().unwrap(), &Default::default()).unwrap();
+        assert_eq!(walked.repos.len(), 1);
+        let repo = &walked.repos[0];
+        assert_eq!(repo.relpath, Path::new("example.com").join(name));
+        assert_eq!(repo.path, canonicalize(&real).unwrap().join(&repo.relpath));
+    }
+}